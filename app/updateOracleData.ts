@@ -56,10 +56,10 @@ async function main() {
 
     // Update the oracle with the fetched price
     await oracleProgram.methods
-      .updateTrendTest(price)
+      .submitPrice(price)
       .accounts({
         oracleState: oracleStatePda,
-        authority: admin.publicKey,
+        publisher: admin.publicKey,
       } as any)
       .rpc();
 
@@ -67,7 +67,7 @@ async function main() {
 
     // Verify the update
     const oracleState = await oracleProgram.account.oracleState.fetch(oracleStatePda);
-    console.log("Oracle trend_value:", oracleState.trendValue.toString());
+    console.log("Oracle submissions:", oracleState.submissions.map((s) => s.price.toString()));
     
   } catch (error) {
     console.error("Error in main:", error);
@@ -1,3 +1,14 @@
+// Stops Rust Analyzer complaining about missing configs
+// See https://solana.stackexchange.com/questions/17777
+#![allow(unexpected_cfgs)]
+
+// Fix warning: use of deprecated method `anchor_lang::prelude::AccountInfo::<'a>::realloc`: Use AccountInfo::resize() instead
+// See https://solana.stackexchange.com/questions/22979
+#![allow(deprecated)]
+
+// set_feed mirrors every PriceAccount field, including in the generated cpi module
+#![allow(clippy::too_many_arguments)]
+
 use anchor_lang::prelude::*;
 
 declare_id!("4GvwyPdEK3PKGoUAhBbLmAgmwgEBi8UqQmEimm7d6Hzg");
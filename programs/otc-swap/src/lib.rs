@@ -7,16 +7,35 @@
 #![allow(deprecated)]
 
 use anchor_lang::prelude::*;
+use anchor_lang::system_program;
 use anchor_lang::solana_program::program_option::COption;
 use anchor_spl::token::{self, SetAuthority, Mint, Token, TokenAccount, Transfer, MintTo, Burn};
 use spl_token::instruction::AuthorityType;
-use pyth_sdk_solana::Price;
-use pyth_sdk_solana::state::SolanaPriceAccount;
+
+pub mod math;
+pub mod oracle;
+pub mod price;
+
+use oracle::PriceSnapshot;
+use price::Price;
 
 
 const CONFIG_MAX_FEE_RATE_BPS: u64 = 500;
 const CONFIG_MIN_COLLATERAL_BPS: u64 = 20_000;
+const CONFIG_MAX_COLLATERAL_BPS: u64 = 100_000;
 const ORACLE_MAX_AGE: u64 = 300;
+const SBTC_ORACLE_MIN_PUBLISHERS: u8 = 3;
+/// Lowest quorum `update_sbtc_oracle_quorum` accepts; a lone publisher only in mock-oracle test builds
+#[cfg(not(feature = "mock-oracle"))]
+const SBTC_ORACLE_QUORUM_FLOOR: u8 = 2;
+#[cfg(feature = "mock-oracle")]
+const SBTC_ORACLE_QUORUM_FLOOR: u8 = 1;
+const SBTC_ORACLE_MAX_SPREAD_BPS: u64 = 200;
+const SBTC_MAX_PRICE_CHANGE_BPS: u64 = 500;
+const SBTC_PRICE_CHANGE_WINDOW: u64 = 3_600;
+const ZBTC_MAX_CONF_BPS: u64 = 10;
+const ZBTC_MAX_EMA_DEVIATION_BPS: u64 = 200;
+const CONFIG_VERSION: u8 = 9;
 
 
 declare_id!("DBHmndyfN4j7BtQsLaCR1SPd7iAXaf1ezUicDs3pUXS8");
@@ -29,11 +48,12 @@ pub mod otc_swap {
         ctx: Context<Initialize>,
         fee_rate_bps: u64,
         min_collateral_bps: u64,
-        authorized_zbtc_pyth_feed: Pubkey,
+        zbtc_pyth_feed_id: [u8; 32],
         authorized_sbtc_oracle_state_pda: Pubkey,
     ) -> Result<()> {
         require!(fee_rate_bps <= CONFIG_MAX_FEE_RATE_BPS, ErrorCode::InvalidFeeRate,);
-        require!(min_collateral_bps >= CONFIG_MIN_COLLATERAL_BPS, ErrorCode::InvalidCollateralRatio,);
+        require!(zbtc_pyth_feed_id != [0u8; 32], ErrorCode::InvalidPythFeedId);
+        check_collateral_ratio(min_collateral_bps)?;
         require!(
             ctx.accounts.sbtc_mint.mint_authority == COption::Some(ctx.accounts.squad_multisig.key()),
             ErrorCode::InvalidMintAuthority,
@@ -79,11 +99,24 @@ pub mod otc_swap {
         config.bump = ctx.bumps.config;
         config.sbtc_decimals = ctx.accounts.sbtc_mint.decimals;
         config.zbtc_decimals = ctx.accounts.zbtc_mint.decimals;
-        config.paused = false;
+        config.mint_paused = false;
+        config.burn_paused = false;
         config.total_sbtc_outstanding = 0u128;
         config.created_at = timestamp;
-        config.authorized_zbtc_pyth_feed = authorized_zbtc_pyth_feed;
+        config.zbtc_pyth_feed_id = zbtc_pyth_feed_id;
         config.authorized_sbtc_oracle_state_pda = authorized_sbtc_oracle_state_pda;
+        config.guardian = None;
+        config.version = CONFIG_VERSION;
+        config.sbtc_oracle_max_age = ORACLE_MAX_AGE;
+        config.sbtc_oracle_program_id = sbtc_oracle::ID;
+        config.sbtc_oracle_min_publishers = SBTC_ORACLE_MIN_PUBLISHERS;
+        config.sbtc_oracle_max_spread_bps = SBTC_ORACLE_MAX_SPREAD_BPS;
+        config.sbtc_max_price_change_bps = SBTC_MAX_PRICE_CHANGE_BPS;
+        config.sbtc_price_change_window = SBTC_PRICE_CHANGE_WINDOW;
+        config.last_sbtc_price_cents = 0;
+        config.last_sbtc_price_at = 0;
+        config.max_conf_bps = ZBTC_MAX_CONF_BPS;
+        config.max_ema_deviation_bps = ZBTC_MAX_EMA_DEVIATION_BPS;
 
         emit!(InitializedEvent {
             squad_multisig: ctx.accounts.squad_multisig.key(),
@@ -93,361 +126,535 @@ pub mod otc_swap {
             fee_vault: ctx.accounts.fee_vault.key(),
             fee_rate_bps,
             min_collateral_bps,
-            timestamp: timestamp,
+            timestamp,
             sbtc_mint_authority: ctx.accounts.sbtc_mint_authority_pda.key(),
             treasury_vault_authority: ctx.accounts.treasury_authority_pda.key(),
             fee_vault_authority: ctx.accounts.fee_authority_pda.key(),
-            authorized_zbtc_pyth_feed: authorized_zbtc_pyth_feed,
-            authorized_sbtc_oracle_state_pda: authorized_sbtc_oracle_state_pda,
+            zbtc_pyth_feed_id,
+            authorized_sbtc_oracle_state_pda,
         });
         
         Ok(())
     }
 
-    pub fn mint_sbtc(ctx: Context<MintSbtc>, zbtc_amount: u64) -> Result<()> {
-        msg!("=== START MINT_SBTC ===");
-
-        // -- 1) basic validation
+    /// Fails with `SlippageExceeded` if fewer than `min_sbtc_out` sBTC would be minted and with
+    /// `DeadlineExceeded` once the clock is past `deadline`.
+    pub fn mint_sbtc(
+        ctx: Context<MintSbtc>,
+        zbtc_amount: u64,
+        min_sbtc_out: u64,
+        deadline: Option<i64>,
+    ) -> Result<()> {
         require!(zbtc_amount > 0, ErrorCode::InvalidAmount);
-        let config: &mut Account<'_, Config> = &mut ctx.accounts.config;
-
-        require!(!config.paused, ErrorCode::Paused);
-        require!(ctx.accounts.zbtc_mint.key() == config.zbtc_mint, ErrorCode::InvalidZbtcMint);
-        require!(ctx.accounts.sbtc_mint.key() == config.sbtc_mint, ErrorCode::InvalidSbtcMint);
-        require!(ctx.accounts.user_zbtc_account.owner == ctx.accounts.user.key(), ErrorCode::InvalidTokenAccountOwner);
-        require!(ctx.accounts.user_sbtc_account.owner == ctx.accounts.user.key(), ErrorCode::InvalidTokenAccountOwner);
-        require!(ctx.accounts.user_zbtc_account.amount >= zbtc_amount, ErrorCode::InsufficientBalance);
-        msg!("DEBUG: Passed all account validations");
-
-        // -- 2) compute fee and net deposit (u128 math)
-        let fee_bps = config.fee_rate_bps as u128;
-        let zbtc_amount_u128 = zbtc_amount as u128;
-        let fee_amount_u128 = zbtc_amount_u128
-            .checked_mul(fee_bps)
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_div(10_000u128) // using bps base
-            .ok_or(ErrorCode::InvalidAmount)?;
 
-        let fee_amount_u64 = fee_amount_u128 as u64; // safe because fee <= zbtc_amount which is u64
-        let net_zbtc_u128 = zbtc_amount_u128.checked_sub(fee_amount_u128).ok_or(ErrorCode::InvalidAmount)?;
-        let net_zbtc_u64 = net_zbtc_u128 as u64;
-        msg!("DEBUG: Fee calculation complete");
+        // -- 1) validate accounts, read zBTC/USD from Pyth and sBTC/USD from the oracle
+        let clock = Clock::get()?;
+        check_deadline(deadline, clock.unix_timestamp)?;
+        let (zbtc_price, sbtc_price) = ctx.accounts.load_prices(&clock)?;
+
+        // -- 2) compute fee and net deposit
+        let config = &ctx.accounts.config;
+        let (fee_amount, net_zbtc) = math::split_fee(zbtc_amount, config.fee_rate_bps)?;
+
+        // -- 3) Calculate sBTC to mint
+        let sbtc_to_mint =
+            math::sbtc_to_mint(net_zbtc, zbtc_price, config.zbtc_decimals, sbtc_price, config.sbtc_decimals)?;
+        require!(sbtc_to_mint >= min_sbtc_out, ErrorCode::SlippageExceeded);
+
+        ctx.accounts.settle(
+            ctx.bumps.sbtc_mint_authority_pda,
+            zbtc_amount,
+            fee_amount,
+            sbtc_to_mint,
+            zbtc_price,
+            sbtc_price,
+            &clock,
+        )
+    }
+
+    /// Mints exactly `sbtc_out` sBTC for the least zBTC that covers it after fees, rounding in
+    /// the protocol's favour. Fails with `SlippageExceeded` if that takes more than `max_zbtc_in`.
+    pub fn mint_sbtc_exact_out(
+        ctx: Context<MintSbtc>,
+        sbtc_out: u64,
+        max_zbtc_in: u64,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        require!(sbtc_out > 0, ErrorCode::InvalidAmount);
 
-        // -- 3) read & validatezBTC/USD price from Pyth feed
-        let pyth_account = &ctx.accounts.authorized_zbtc_pyth_feed;
         let clock = Clock::get()?;
+        check_deadline(deadline, clock.unix_timestamp)?;
+        let (zbtc_price, sbtc_price) = ctx.accounts.load_prices(&clock)?;
+
+        let config = &ctx.accounts.config;
+        let net_zbtc =
+            math::zbtc_for_sbtc_out(sbtc_out, zbtc_price, config.zbtc_decimals, sbtc_price, config.sbtc_decimals)?;
+        let zbtc_amount = math::gross_for_net(net_zbtc, config.fee_rate_bps)?;
+        require!(zbtc_amount <= max_zbtc_in, ErrorCode::SlippageExceeded);
+        let (fee_amount, _) = math::split_fee(zbtc_amount, config.fee_rate_bps)?;
+
+        ctx.accounts.settle(
+            ctx.bumps.sbtc_mint_authority_pda,
+            zbtc_amount,
+            fee_amount,
+            sbtc_out,
+            zbtc_price,
+            sbtc_price,
+            &clock,
+        )
+    }
 
-        let (price, conf, expo, publish_time) = match SolanaPriceAccount::account_info_to_feed(pyth_account) {
-            Ok(price_feed) => {
-                msg!("DEBUG: Real Pyth feed loaded");
-                let current_time = clock.unix_timestamp;
-                let current_price_opt: Option<Price> = price_feed.get_price_no_older_than(current_time, ORACLE_MAX_AGE);
+    /// Fails with `SlippageExceeded` if fewer than `min_zbtc_out` zBTC would be paid out after
+    /// fees and with `DeadlineExceeded` once the clock is past `deadline`.
+    pub fn burn_sbtc(
+        ctx: Context<BurnSbtc>,
+        sbtc_amount: u64,
+        min_zbtc_out: u64,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        require!(sbtc_amount > 0, ErrorCode::InvalidAmount);
 
-                let price: Price = current_price_opt.ok_or(ErrorCode::StaleOraclePrice)?;
-                require!(price.conf < price.price.unsigned_abs() / 1000u64, ErrorCode::HighConfidence);
-                msg!("DEBUG: Pyth price confidence check passed");
-                (price.price, price.conf, price.expo, price.publish_time)
-            }
-            Err(_) => {
-                msg!("DEBUG: Falling back to mock Pyth format");
-                let account_data = pyth_account.try_borrow_data()?;
-                let data_start = 8; // Skip Anchor discriminator
-                
-                if account_data.len() < data_start + 28 {
-                    return Err(ErrorCode::InvalidPythAccount.into());
-                }
-                
-                let price = i64::from_le_bytes(account_data[data_start..data_start+8].try_into().unwrap());
-                let conf = u64::from_le_bytes(account_data[data_start+8..data_start+16].try_into().unwrap());
-                let expo = i32::from_le_bytes(account_data[data_start+16..data_start+20].try_into().unwrap());
-                let publish_time = i64::from_le_bytes(account_data[data_start+20..data_start+28].try_into().unwrap());
-                
-                (price, conf, expo, publish_time)
+        // -- 1) Get zBTC/USD price from Pyth and sBTC price from your oracle
+        let clock = Clock::get()?;
+        check_deadline(deadline, clock.unix_timestamp)?;
+        let (zbtc_price, sbtc_price) = ctx.accounts.load_prices(&clock)?;
+
+        // -- 2) Calculate zBTC to redeem
+        // zbtc_to_redeem = (sbtc_amount * sbtc_price * 10^zbtc_decimals) / (zbtc_price * 10^sbtc_decimals)
+        let config = &ctx.accounts.config;
+        let mut zbtc_to_redeem =
+            math::zbtc_to_redeem(sbtc_amount, sbtc_price, config.sbtc_decimals, zbtc_price, config.zbtc_decimals)?;
+
+        // Below the collateral buffer each sBTC redeems for at most its share of the treasury
+        let treasury_balance = ctx.accounts.treasury_zbtc_vault.amount;
+        let mut pro_rata = false;
+        if pro_rata_mode(config, treasury_balance, zbtc_price, sbtc_price)? {
+            let share = math::pro_rata_zbtc(sbtc_amount, treasury_balance, config.total_sbtc_outstanding)?;
+            if share < zbtc_to_redeem {
+                zbtc_to_redeem = share;
+                pro_rata = true;
             }
-        };
+        }
 
-        msg!("price: {}, conf: {}, expo: {}, publish_time: {}", price, conf, expo, publish_time);
-
-        let zbtc_price_cents: u64 = if price >= 0 {
-            let actual_expo = expo + 2;
-            
-            if actual_expo >= 0 {
-                (price as u64).checked_mul(10u64.pow(actual_expo as u32))
-                    .ok_or(ErrorCode::InvalidPrice)?
-            } else {
-                (price as u64).checked_div(10u64.pow((-actual_expo) as u32))
-                    .ok_or(ErrorCode::InvalidPrice)?
-            }
-        } else {
-            return Err(ErrorCode::InvalidPrice.into());
-        };
+        // -- 3) Calculate fee and net redemption
+        let (fee_amount, net_zbtc) = math::split_fee(zbtc_to_redeem, config.fee_rate_bps)?;
+        require!(net_zbtc > 0, ErrorCode::InvalidAmount);
+        require!(net_zbtc >= min_zbtc_out, ErrorCode::SlippageExceeded);
 
-        // -- 4) Get sBTC price from oracle
-        let oracle_account_data = ctx.accounts.authorized_sbtc_oracle_state_pda.try_borrow_data()?;
-        msg!("DEBUG: Oracle account data length: {}", oracle_account_data.len());
+        ctx.accounts.settle(
+            ctx.bumps.treasury_authority_pda,
+            sbtc_amount,
+            fee_amount,
+            net_zbtc,
+            pro_rata,
+            zbtc_price,
+            sbtc_price,
+            &clock,
+        )
+    }
 
-        require!(oracle_account_data.len() >= 24, ErrorCode::InvalidOracleData); // 8 discriminator + 8 trend_value + 8 last_update
+    /// Pays out exactly `zbtc_out` zBTC after fees for the least sBTC that covers it, rounding in
+    /// the protocol's favour. Fails with `SlippageExceeded` if that burns more than `max_sbtc_in`.
+    pub fn burn_sbtc_exact_out(
+        ctx: Context<BurnSbtc>,
+        zbtc_out: u64,
+        max_sbtc_in: u64,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        require!(zbtc_out > 0, ErrorCode::InvalidAmount);
 
-        let _discriminator = &oracle_account_data[0..8];
-        msg!("DEBUG: Discriminator read successfully");
+        let clock = Clock::get()?;
+        check_deadline(deadline, clock.unix_timestamp)?;
+        let (zbtc_price, sbtc_price) = ctx.accounts.load_prices(&clock)?;
+
+        let config = &ctx.accounts.config;
+        let zbtc_to_redeem = math::gross_for_net(zbtc_out, config.fee_rate_bps)?;
+        let mut sbtc_amount =
+            math::sbtc_for_zbtc_out(zbtc_to_redeem, sbtc_price, config.sbtc_decimals, zbtc_price, config.zbtc_decimals)?;
+
+        // burn_sbtc pays the lesser of oracle value and pro-rata share, so cover both
+        let treasury_balance = ctx.accounts.treasury_zbtc_vault.amount;
+        let mut pro_rata = false;
+        if pro_rata_mode(config, treasury_balance, zbtc_price, sbtc_price)? {
+            let pro_rata_sbtc =
+                math::sbtc_for_pro_rata_out(zbtc_to_redeem, treasury_balance, config.total_sbtc_outstanding)?;
+            if pro_rata_sbtc > sbtc_amount {
+                sbtc_amount = pro_rata_sbtc;
+                pro_rata = true;
+            }
+        }
+        require!(sbtc_amount <= max_sbtc_in, ErrorCode::SlippageExceeded);
+        let (fee_amount, _) = math::split_fee(zbtc_to_redeem, config.fee_rate_bps)?;
 
-        let oracle_data = &oracle_account_data[8..]; // Skip discriminator
-        let sbtc_price_cents = u64::from_le_bytes(oracle_data[0..8].try_into().unwrap());
-        msg!("DEBUG: trend_value bytes read successfully");
-        let last_update = i64::from_le_bytes(oracle_data[8..16].try_into().unwrap());
+        ctx.accounts.settle(
+            ctx.bumps.treasury_authority_pda,
+            sbtc_amount,
+            fee_amount,
+            zbtc_out,
+            pro_rata,
+            zbtc_price,
+            sbtc_price,
+            &clock,
+        )
+    }
 
-        msg!("DEBUG: Read sbtc_price_cents: {}", sbtc_price_cents);
-        msg!("DEBUG: Read last_update: {}", last_update);
+    /// Previews `mint_sbtc(zbtc_amount)` at current oracle prices without moving any funds.
+    /// Fails wherever `mint_sbtc` would; meant to be simulated.
+    pub fn quote_mint(ctx: Context<TreasuryView>, zbtc_amount: u64) -> Result<Quote> {
+        require!(zbtc_amount > 0, ErrorCode::InvalidAmount);
+        require!(!ctx.accounts.config.mint_paused, ErrorCode::Paused);
 
-        // mainnet only - check if oracle data is recent enough
-        let current_timestamp = clock.unix_timestamp;
-        // require!(current_timestamp - last_update <= 300, ErrorCode::StaleOraclePrice);
+        let clock = Clock::get()?;
+        let prices = ctx.accounts.load_prices(&clock)?;
+        // Same side of the confidence interval as mint_sbtc
+        let (zbtc_price, sbtc_price) = (prices.zbtc_bid, prices.sbtc_price);
 
-        // -- 5) Calculate sBTC to mint        
-        let zbtc_decimals = config.zbtc_decimals;
-        let sbtc_decimals = config.sbtc_decimals;
+        let config = &ctx.accounts.config;
+        let (fee, net_zbtc) = math::split_fee(zbtc_amount, config.fee_rate_bps)?;
+        let sbtc_out =
+            math::sbtc_to_mint(net_zbtc, zbtc_price, config.zbtc_decimals, sbtc_price, config.sbtc_decimals)?;
 
-        msg!("DEBUG: zbtc_price_cents: {}", zbtc_price_cents);
-        msg!("DEBUG: sbtc_price_cents: {}", sbtc_price_cents);
-        msg!("DEBUG: net_zbtc_u128: {}", net_zbtc_u128);
-        msg!("DEBUG: zbtc_decimals: {}", zbtc_decimals);
-        msg!("DEBUG: sbtc_decimals: {}", sbtc_decimals);
-        
-        // Convert net zBTC amount to USD value (in cents)
-        let net_zbtc_value_cents = net_zbtc_u128
-            .checked_mul(zbtc_price_cents as u128)
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_div(10u128.pow(zbtc_decimals as u32))
+        let treasury_after = ctx.accounts.treasury_zbtc_vault.amount
+            .checked_add(net_zbtc)
             .ok_or(ErrorCode::InvalidAmount)?;
-
-        let sbtc_to_mint_u128 = net_zbtc_u128
-            .checked_mul(zbtc_price_cents as u128)
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_mul(10u128.pow(sbtc_decimals as u32))
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_div(sbtc_price_cents as u128)
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_div(10u128.pow(zbtc_decimals as u32))
+        let outstanding_after = config.total_sbtc_outstanding
+            .checked_add(sbtc_out as u128)
             .ok_or(ErrorCode::InvalidAmount)?;
 
-        require!(sbtc_to_mint_u128 > 0, ErrorCode::InvalidAmount);
-        require!(sbtc_to_mint_u128 <= u64::MAX as u128, ErrorCode::InvalidAmount);
-        let sbtc_to_mint_u64 = sbtc_to_mint_u128 as u64;
-
-        msg!("DEBUG: net_zbtc_value_cents: {}", net_zbtc_value_cents);
-        msg!("DEBUG: sbtc_to_mint_u128: {}", sbtc_to_mint_u128);
-        msg!("DEBUG: sbtc_to_mint_u64: {}", sbtc_to_mint_u64);
+        Ok(Quote {
+            output: sbtc_out,
+            fee,
+            zbtc_price_cents: zbtc_price.to_cents(),
+            sbtc_price_cents: sbtc_price.to_cents(),
+            collateral_ratio_after: quoted_collateral_ratio(config, treasury_after, outstanding_after, zbtc_price, sbtc_price)?,
+        })
+    }
 
-        // -- 6) Transfer zBTC to treasury and fee vault
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.user_zbtc_account.to_account_info(),
-                    to: ctx.accounts.treasury_zbtc_vault.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
-                },
-            ),
-            net_zbtc_u64,
-        )?;
+    /// Previews `burn_sbtc(sbtc_amount)` at current oracle prices without moving any funds.
+    /// `output` is the net zBTC paid out. Fails wherever `burn_sbtc` would; meant to be simulated.
+    pub fn quote_burn(ctx: Context<TreasuryView>, sbtc_amount: u64) -> Result<Quote> {
+        require!(sbtc_amount > 0, ErrorCode::InvalidAmount);
+        require!(!ctx.accounts.config.burn_paused, ErrorCode::Paused);
 
-        if fee_amount_u64 > 0 {
-            token::transfer(
-                CpiContext::new(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.user_zbtc_account.to_account_info(),
-                        to: ctx.accounts.fee_vault.to_account_info(),
-                        authority: ctx.accounts.user.to_account_info(),
-                    },
-                ),
-                fee_amount_u64,
-            )?;
+        let clock = Clock::get()?;
+        let prices = ctx.accounts.load_prices(&clock)?;
+        // Same side of the confidence interval as burn_sbtc
+        let (zbtc_price, sbtc_price) = (prices.zbtc_ask, prices.sbtc_price);
+
+        let config = &ctx.accounts.config;
+        let mut zbtc_to_redeem =
+            math::zbtc_to_redeem(sbtc_amount, sbtc_price, config.sbtc_decimals, zbtc_price, config.zbtc_decimals)?;
+        let treasury_balance = ctx.accounts.treasury_zbtc_vault.amount;
+        if pro_rata_mode(config, treasury_balance, zbtc_price, sbtc_price)? {
+            let share = math::pro_rata_zbtc(sbtc_amount, treasury_balance, config.total_sbtc_outstanding)?;
+            zbtc_to_redeem = zbtc_to_redeem.min(share);
         }
+        let (fee, net_zbtc) = math::split_fee(zbtc_to_redeem, config.fee_rate_bps)?;
+        require!(net_zbtc > 0, ErrorCode::InvalidAmount);
+
+        // The fee leaves the treasury along with the net redemption
+        let treasury_after = treasury_balance
+            .checked_sub(zbtc_to_redeem)
+            .ok_or(ErrorCode::InsufficientLiquidity)?;
+        let outstanding_after = config.total_sbtc_outstanding
+            .checked_sub(sbtc_amount as u128)
+            .ok_or(ErrorCode::InvalidAmount)?;
+        // Redemptions have no collateral requirement, so report the ratio without enforcing one
+        let backing_after = math::backing_zbtc(
+            outstanding_after,
+            sbtc_price,
+            config.sbtc_decimals,
+            zbtc_price,
+            config.zbtc_decimals,
+        )?;
 
-        // -- 7) Mint sBTC to user
-        let seeds = &[
-            b"sbtc_mint_authority",
-            ctx.accounts.squad_multisig.key.as_ref(),
-            &[ctx.bumps.sbtc_mint_authority_pda],
-        ];
-        let signer_seeds = &[&seeds[..]];
+        Ok(Quote {
+            output: net_zbtc,
+            fee,
+            zbtc_price_cents: zbtc_price.to_cents(),
+            sbtc_price_cents: sbtc_price.to_cents(),
+            collateral_ratio_after: math::collateral_ratio_bps(treasury_after, backing_after),
+        })
+    }
 
-        let cpi_accounts = MintTo {
-            mint: ctx.accounts.sbtc_mint.to_account_info(),
-            to: ctx.accounts.user_sbtc_account.to_account_info(),
-            authority: ctx.accounts.sbtc_mint_authority_pda.to_account_info(),
+    /// Permissionless solvency report at current oracle prices, returned as `SystemHealth` and
+    /// emitted as a `HealthEvent`.
+    pub fn get_system_health(ctx: Context<TreasuryView>) -> Result<SystemHealth> {
+        let clock = Clock::get()?;
+        let config = &ctx.accounts.config;
+        let prices = PriceSnapshot::load(
+            config,
+            &ctx.accounts.zbtc_price_update,
+            &ctx.accounts.authorized_sbtc_oracle_state_pda,
+            &clock,
+        )?;
+        let zbtc_price = prices.zbtc_price;
+        let sbtc_price = prices.sbtc_price;
+
+        let treasury_balance = ctx.accounts.treasury_zbtc_vault.amount;
+        let health = SystemHealth {
+            treasury_balance,
+            total_sbtc_outstanding: config.total_sbtc_outstanding,
+            required_collateral: required_collateral_zbtc(config, zbtc_price, sbtc_price)?,
+            collateral_ratio_bps: collateral_ratio_bps(config, treasury_balance, zbtc_price, sbtc_price)?,
         };
 
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        token::mint_to(
-            CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds),
-            sbtc_to_mint_u64,
-        )?;
+        emit!(HealthEvent {
+            treasury_balance,
+            total_sbtc_outstanding: health.total_sbtc_outstanding,
+            required_collateral: health.required_collateral,
+            collateral_ratio_bps: health.collateral_ratio_bps,
+            min_collateral_bps: config.min_collateral_bps,
+            zbtc_price_cents: zbtc_price.to_cents(),
+            sbtc_price_cents: sbtc_price.to_cents(),
+            timestamp: clock.unix_timestamp,
+        });
 
-        // -- 8) Update accounting
-        config.total_sbtc_outstanding = config.total_sbtc_outstanding
-            .checked_add(sbtc_to_mint_u64 as u128)
-            .ok_or(ErrorCode::InvalidAmount)?;
+        Ok(health)
+    }
 
-        // -- 9) Collateral check
-        let treasury_balance = ctx.accounts.treasury_zbtc_vault.amount as u128;
-        
-        // Calculate required collateral: total_sbtc_outstanding * sbtc_price / zbtc_price
-        let required_zbtc_minor = config.total_sbtc_outstanding
-            .checked_mul(sbtc_price_cents as u128)
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_mul(10u128.pow(zbtc_decimals as u32))
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_div(zbtc_price_cents as u128)
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_div(10u128.pow(sbtc_decimals as u32))
-            .ok_or(ErrorCode::InvalidAmount)?;
+    // ========================= Admin ================================
+    pub fn update_fee_rate(ctx: Context<UpdateConfig>, fee_rate_bps: u64) -> Result<()> {
+        require!(fee_rate_bps <= CONFIG_MAX_FEE_RATE_BPS, ErrorCode::InvalidFeeRate);
 
-        // Apply collateral buffer
-        let min_collateral_bps = config.min_collateral_bps as u128;
-        let required_zbtc_with_buffer = required_zbtc_minor
-            .checked_mul(min_collateral_bps)
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_div(10_000u128)
-            .ok_or(ErrorCode::InvalidAmount)?;
+        let config = &mut ctx.accounts.config;
+        let old_params = config.params();
+        config.fee_rate_bps = fee_rate_bps;
 
-        require!(treasury_balance >= required_zbtc_with_buffer, ErrorCode::InsufficientCollateral);
+        emit_config_updated(config, old_params)
+    }
 
-        // -- 10) Emit event
-        emit!(MintEvent {
-            user: ctx.accounts.user.key(),
-            zbtc_deposited: zbtc_amount,
-            sbtc_minted: sbtc_to_mint_u64 as u128,
-            fee_amount: fee_amount_u64,
-            zbtc_price_cents,
-            sbtc_price_cents,
-            timestamp: Clock::get()?.unix_timestamp,
-        });
+    pub fn update_collateral_ratio(ctx: Context<UpdateConfig>, min_collateral_bps: u64) -> Result<()> {
+        check_collateral_ratio(min_collateral_bps)?;
 
-        Ok(())
+        let config = &mut ctx.accounts.config;
+        let old_params = config.params();
+        config.min_collateral_bps = min_collateral_bps;
+
+        emit_config_updated(config, old_params)
     }
 
-    pub fn burn_sbtc(ctx: Context<BurnSbtc>, sbtc_amount: u64) -> Result<()> {
-        require!(sbtc_amount > 0, ErrorCode::InvalidAmount);
+    pub fn update_oracles(
+        ctx: Context<UpdateConfig>,
+        zbtc_pyth_feed_id: [u8; 32],
+        authorized_sbtc_oracle_state_pda: Pubkey,
+        sbtc_oracle_program_id: Pubkey,
+    ) -> Result<()> {
+        require!(zbtc_pyth_feed_id != [0u8; 32], ErrorCode::InvalidPythFeedId);
+        require!(sbtc_oracle_program_id != Pubkey::default(), ErrorCode::InvalidOracleAccount);
 
         let config = &mut ctx.accounts.config;
-        require!(!config.paused, ErrorCode::Paused);
-        require!(ctx.accounts.zbtc_mint.key() == config.zbtc_mint, ErrorCode::InvalidZbtcMint);
-        require!(ctx.accounts.sbtc_mint.key() == config.sbtc_mint, ErrorCode::InvalidSbtcMint);
-        require!(ctx.accounts.user_sbtc_account.owner == ctx.accounts.user.key(), ErrorCode::InvalidTokenAccountOwner);
-        require!(ctx.accounts.user_zbtc_account.owner == ctx.accounts.user.key(), ErrorCode::InvalidTokenAccountOwner);
-        require!(ctx.accounts.user_sbtc_account.amount >= sbtc_amount, ErrorCode::InsufficientBalance);
-
-        // -- 1) Get zBTC/USD price from Pyth
-        let pyth_account = &ctx.accounts.authorized_zbtc_pyth_feed;
-        let clock = Clock::get()?;
+        let old_params = config.params();
+        config.zbtc_pyth_feed_id = zbtc_pyth_feed_id;
+        config.authorized_sbtc_oracle_state_pda = authorized_sbtc_oracle_state_pda;
+        config.sbtc_oracle_program_id = sbtc_oracle_program_id;
 
-        let (price, conf, expo, publish_time) = match SolanaPriceAccount::account_info_to_feed(pyth_account) {
-            Ok(price_feed) => {
-                msg!("DEBUG: Real Pyth feed loaded");
-                let current_time = clock.unix_timestamp;
-                let current_price_opt: Option<Price> = price_feed.get_price_no_older_than(current_time, ORACLE_MAX_AGE);
+        emit_config_updated(config, old_params)
+    }
 
-                let price: Price = current_price_opt.ok_or(ErrorCode::StaleOraclePrice)?;
-                require!(price.conf < price.price.unsigned_abs() / 1000u64, ErrorCode::HighConfidence);
-                msg!("DEBUG: Pyth price confidence check passed");
-                (price.price, price.conf, price.expo, price.publish_time)
-            }
-            Err(_) => {
-                msg!("DEBUG: Falling back to mock Pyth format");
-                let account_data = pyth_account.try_borrow_data()?;
-                let data_start = 8; // Skip Anchor discriminator
-                
-                if account_data.len() < data_start + 28 {
-                    return Err(ErrorCode::InvalidPythAccount.into());
-                }
-                
-                let price = i64::from_le_bytes(account_data[data_start..data_start+8].try_into().unwrap());
-                let conf = u64::from_le_bytes(account_data[data_start+8..data_start+16].try_into().unwrap());
-                let expo = i32::from_le_bytes(account_data[data_start+16..data_start+20].try_into().unwrap());
-                let publish_time = i64::from_le_bytes(account_data[data_start+20..data_start+28].try_into().unwrap());
-                
-                (price, conf, expo, publish_time)
-            }
-        };
+    pub fn update_sbtc_oracle_max_age(ctx: Context<UpdateConfig>, sbtc_oracle_max_age: u64) -> Result<()> {
+        require!(
+            sbtc_oracle_max_age > 0 && sbtc_oracle_max_age <= i64::MAX as u64,
+            ErrorCode::InvalidOracleMaxAge,
+        );
 
-        msg!("price: {}, conf: {}, expo: {}, publish_time: {}", price, conf, expo, publish_time);
-
-        let zbtc_price_cents: u64 = if price >= 0 {
-            let actual_expo = expo + 2;
-            
-            if actual_expo >= 0 {
-                (price as u64).checked_mul(10u64.pow(actual_expo as u32))
-                    .ok_or(ErrorCode::InvalidPrice)?
-            } else {
-                (price as u64).checked_div(10u64.pow((-actual_expo) as u32))
-                    .ok_or(ErrorCode::InvalidPrice)?
-            }
-        } else {
-            return Err(ErrorCode::InvalidPrice.into());
-        };
+        let config = &mut ctx.accounts.config;
+        let old_params = config.params();
+        config.sbtc_oracle_max_age = sbtc_oracle_max_age;
 
-        // -- 2) Get sBTC price from your oracle
-        let oracle_account_data = ctx.accounts.authorized_sbtc_oracle_state_pda.try_borrow_data()?;
-        let oracle_data = &oracle_account_data[8..];
+        emit_config_updated(config, old_params)
+    }
 
-        let sbtc_price_cents = u64::from_le_bytes(oracle_data[0..8].try_into().unwrap());
-        let last_update = i64::from_le_bytes(oracle_data[8..16].try_into().unwrap());
+    pub fn update_sbtc_oracle_quorum(
+        ctx: Context<UpdateConfig>,
+        sbtc_oracle_min_publishers: u8,
+        sbtc_oracle_max_spread_bps: u64,
+    ) -> Result<()> {
+        require!(
+            sbtc_oracle_min_publishers >= SBTC_ORACLE_QUORUM_FLOOR
+                && sbtc_oracle_min_publishers as usize <= sbtc_oracle::MAX_PUBLISHERS,
+            ErrorCode::InvalidOracleQuorum,
+        );
+        require!(sbtc_oracle_max_spread_bps <= 10_000, ErrorCode::InvalidOracleQuorum);
 
-        // mainnet only
-        // require!(current_time - last_update <= 300, ErrorCode::StaleOraclePrice);
+        let config = &mut ctx.accounts.config;
+        let old_params = config.params();
+        config.sbtc_oracle_min_publishers = sbtc_oracle_min_publishers;
+        config.sbtc_oracle_max_spread_bps = sbtc_oracle_max_spread_bps;
 
-        // -- 3) Calculate zBTC to redeem
-        let zbtc_decimals = config.zbtc_decimals;
-        let sbtc_decimals = config.sbtc_decimals;
-        
-        // zbtc_to_redeem = (sbtc_amount * sbtc_price_cents * 10^zbtc_decimals) / (zbtc_price_cents * 10^sbtc_decimals)
-        let zbtc_to_redeem_u128 = (sbtc_amount as u128)
-            .checked_mul(sbtc_price_cents as u128)
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_mul(10u128.pow(zbtc_decimals as u32))
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_div(zbtc_price_cents as u128)
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_div(10u128.pow(sbtc_decimals as u32))
-            .ok_or(ErrorCode::InvalidAmount)?;
+        emit_config_updated(config, old_params)
+    }
+
+    /// Updates the sBTC price circuit breaker and clears its anchor, so the next mint or burn
+    /// re-anchors at the then-current oracle price.
+    pub fn update_sbtc_price_breaker(
+        ctx: Context<UpdateConfig>,
+        sbtc_max_price_change_bps: u64,
+        sbtc_price_change_window: u64,
+    ) -> Result<()> {
+        require!(sbtc_max_price_change_bps > 0, ErrorCode::InvalidPriceBreaker);
+        require!(
+            sbtc_price_change_window > 0 && sbtc_price_change_window <= i64::MAX as u64,
+            ErrorCode::InvalidPriceBreaker,
+        );
 
-        require!(zbtc_to_redeem_u128 > 0, ErrorCode::InvalidAmount);
-        require!(zbtc_to_redeem_u128 <= u64::MAX as u128, ErrorCode::InvalidAmount);
-        let zbtc_to_redeem_u64 = zbtc_to_redeem_u128 as u64;
+        let config = &mut ctx.accounts.config;
+        let old_params = config.params();
+        config.sbtc_max_price_change_bps = sbtc_max_price_change_bps;
+        config.sbtc_price_change_window = sbtc_price_change_window;
+        config.last_sbtc_price_cents = 0;
+        config.last_sbtc_price_at = 0;
 
-        // -- 4) Calculate fee and net redemption
-        let fee_bps = config.fee_rate_bps as u128;
-        let fee_amount_u128 = zbtc_to_redeem_u128
-            .checked_mul(fee_bps)
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_div(10_000u128)
-            .ok_or(ErrorCode::InvalidAmount)?;
+        emit_config_updated(config, old_params)
+    }
 
-        let fee_amount_u64 = fee_amount_u128 as u64;
-        let net_zbtc_u128 = zbtc_to_redeem_u128.checked_sub(fee_amount_u128).ok_or(ErrorCode::InvalidAmount)?;
-        let net_zbtc_u64 = net_zbtc_u128 as u64;
+    pub fn update_max_conf(ctx: Context<UpdateConfig>, max_conf_bps: u64) -> Result<()> {
+        require!(max_conf_bps > 0 && max_conf_bps < 10_000, ErrorCode::InvalidMaxConf);
 
-        require!(net_zbtc_u64 > 0, ErrorCode::InvalidAmount);
+        let config = &mut ctx.accounts.config;
+        let old_params = config.params();
+        config.max_conf_bps = max_conf_bps;
 
-        // -- 5) Treasury liquidity check
-        require!(ctx.accounts.treasury_zbtc_vault.amount >= zbtc_to_redeem_u64, ErrorCode::InsufficientLiquidity);
+        emit_config_updated(config, old_params)
+    }
 
-        // -- 6) Burn sBTC
-        token::burn(
+    pub fn update_max_ema_deviation(ctx: Context<UpdateConfig>, max_ema_deviation_bps: u64) -> Result<()> {
+        require!(
+            max_ema_deviation_bps > 0 && max_ema_deviation_bps <= 10_000,
+            ErrorCode::InvalidEmaBand,
+        );
+
+        let config = &mut ctx.accounts.config;
+        let old_params = config.params();
+        config.max_ema_deviation_bps = max_ema_deviation_bps;
+
+        emit_config_updated(config, old_params)
+    }
+
+    pub fn set_guardian(ctx: Context<UpdateConfig>, guardian: Option<Pubkey>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let old_params = config.params();
+        config.guardian = guardian;
+
+        emit_config_updated(config, old_params)
+    }
+
+    /// Halts mint and/or burn. Callable by the squad multisig or the guardian.
+    pub fn pause(ctx: Context<Pause>, pause_mint: bool, pause_burn: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.mint_paused |= pause_mint;
+        config.burn_paused |= pause_burn;
+
+        emit!(PauseEvent {
+            authority: ctx.accounts.authority.key(),
+            mint_paused: config.mint_paused,
+            burn_paused: config.burn_paused,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Resumes mint and/or burn. Only the squad multisig can unpause.
+    pub fn unpause(ctx: Context<UpdateConfig>, unpause_mint: bool, unpause_burn: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.mint_paused &= !unpause_mint;
+        config.burn_paused &= !unpause_burn;
+
+        emit!(PauseEvent {
+            authority: ctx.accounts.squad_multisig.key(),
+            mint_paused: config.mint_paused,
+            burn_paused: config.burn_paused,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Permissionless top-up of the treasury vault, tracked per depositor.
+    pub fn deposit_collateral(ctx: Context<DepositCollateral>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(ctx.accounts.depositor_zbtc_account.amount >= amount, ErrorCode::InsufficientBalance);
+
+        let clock = Clock::get()?;
+
+        token::transfer(
             CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
-                Burn {
-                    mint: ctx.accounts.sbtc_mint.to_account_info(),
-                    from: ctx.accounts.user_sbtc_account.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.depositor_zbtc_account.to_account_info(),
+                    to: ctx.accounts.treasury_zbtc_vault.to_account_info(),
+                    authority: ctx.accounts.depositor.to_account_info(),
                 },
             ),
-            sbtc_amount,
+            amount,
+        )?;
+        ctx.accounts.treasury_zbtc_vault.reload()?;
+
+        let record = &mut ctx.accounts.depositor_record;
+        if record.depositor == Pubkey::default() {
+            record.depositor = ctx.accounts.depositor.key();
+            record.config = ctx.accounts.config.key();
+            record.bump = ctx.bumps.depositor_record;
+        }
+        record.total_deposited = record.total_deposited
+            .checked_add(amount)
+            .ok_or(ErrorCode::InvalidAmount)?;
+        record.last_deposit_at = clock.unix_timestamp;
+
+        // Best effort: top-ups matter most during oracle incidents, so they never depend on prices
+        let treasury_balance = ctx.accounts.treasury_zbtc_vault.amount;
+        let prices = PriceSnapshot::load(
+            &ctx.accounts.config,
+            &ctx.accounts.zbtc_price_update,
+            &ctx.accounts.authorized_sbtc_oracle_state_pda,
+            &clock,
+        )
+        .ok();
+        let collateral_ratio_bps = prices.and_then(|prices| {
+            collateral_ratio_bps(&ctx.accounts.config, treasury_balance, prices.zbtc_price, prices.sbtc_price).ok()
+        });
+
+        emit!(CollateralDepositedEvent {
+            depositor: ctx.accounts.depositor.key(),
+            amount,
+            total_deposited: record.total_deposited,
+            treasury_balance,
+            collateral_ratio_bps,
+            zbtc_price_cents: prices.map(|prices| prices.zbtc_price.to_cents()),
+            sbtc_price_cents: prices.map(|prices| prices.sbtc_price.to_cents()),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraws treasury zBTC above the `min_collateral_bps` requirement, valuing zBTC at its
+    /// bid like the collateral check in `mint_sbtc`.
+    pub fn withdraw_excess_collateral(ctx: Context<WithdrawExcessCollateral>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        let clock = Clock::get()?;
+        let prices = PriceSnapshot::load(
+            &ctx.accounts.config,
+            &ctx.accounts.zbtc_price_update,
+            &ctx.accounts.authorized_sbtc_oracle_state_pda,
+            &clock,
         )?;
+        // Same sBTC circuit breaker as swaps, without moving its anchor
+        oracle::check_sbtc_price_change(&ctx.accounts.config, prices.sbtc_price.to_cents(), clock.unix_timestamp)?;
+        // Same conservative side as the collateral check ending mint_sbtc, so a withdrawal never
+        // leaves the treasury where the next mint fails
+        let zbtc_price = prices.zbtc_bid;
+        let sbtc_price = prices.sbtc_price;
+
+        let treasury_balance = ctx.accounts.treasury_zbtc_vault.amount as u128;
+        let required_zbtc_with_buffer = required_collateral_zbtc(&ctx.accounts.config, zbtc_price, sbtc_price)?;
+        let excess_zbtc = treasury_balance.saturating_sub(required_zbtc_with_buffer);
+
+        require!(amount as u128 <= excess_zbtc, ErrorCode::InsufficientCollateral);
+        let required_collateral = u64::try_from(required_zbtc_with_buffer).map_err(|_| ErrorCode::InvalidAmount)?;
 
-        // -- 7) Transfer net redemption to user
         let seeds: &[&[u8]] = &[
             b"treasury_auth_v1",
             ctx.accounts.squad_multisig.key.as_ref(),
@@ -460,65 +667,107 @@ pub mod otc_swap {
                 ctx.accounts.token_program.to_account_info(),
                 Transfer {
                     from: ctx.accounts.treasury_zbtc_vault.to_account_info(),
-                    to: ctx.accounts.user_zbtc_account.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
                     authority: ctx.accounts.treasury_authority_pda.to_account_info(),
                 },
                 signer_seeds,
             ),
-            net_zbtc_u64,
+            amount,
         )?;
 
-        // -- 8) Transfer fee to fee vault
-        if fee_amount_u64 > 0 {
-            token::transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.treasury_zbtc_vault.to_account_info(),
-                        to: ctx.accounts.fee_vault.to_account_info(),
-                        authority: ctx.accounts.treasury_authority_pda.to_account_info(),
-                    },
-                    signer_seeds,
-                ),
-                fee_amount_u64,
-            )?;
-        }
+        emit!(ExcessCollateralWithdrawnEvent {
+            squad_multisig: ctx.accounts.squad_multisig.key(),
+            destination: ctx.accounts.destination.key(),
+            amount,
+            treasury_balance: ctx.accounts.treasury_zbtc_vault.amount - amount,
+            required_collateral,
+            zbtc_price_cents: zbtc_price.to_cents(),
+            sbtc_price_cents: sbtc_price.to_cents(),
+            timestamp: clock.unix_timestamp,
+        });
 
-        // -- 9) Update accounting
-        config.total_sbtc_outstanding = config.total_sbtc_outstanding
-            .checked_sub(sbtc_amount as u128)
-            .ok_or(ErrorCode::InvalidAmount)?;
+        Ok(())
+    }
 
-        // -- 10) Collateral check after burn
-        let treasury_balance = ctx.accounts.treasury_zbtc_vault.amount as u128;
-        
-        let required_zbtc_minor = config.total_sbtc_outstanding
-            .checked_mul(sbtc_price_cents as u128)
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_mul(10u128.pow(zbtc_decimals as u32))
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_div(zbtc_price_cents as u128)
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_div(10u128.pow(sbtc_decimals as u32))
-            .ok_or(ErrorCode::InvalidAmount)?;
+    /// Moves collected fees out of the fee vault. `None` withdraws the full balance.
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: Option<u64>) -> Result<()> {
+        let fee_balance = ctx.accounts.fee_vault.amount;
+        let amount = amount.unwrap_or(fee_balance);
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(amount <= fee_balance, ErrorCode::InsufficientBalance);
 
-        let min_collateral_bps = config.min_collateral_bps as u128;
-        let required_zbtc_with_buffer = required_zbtc_minor
-            .checked_mul(min_collateral_bps)
-            .ok_or(ErrorCode::InvalidAmount)?
-            .checked_div(10_000u128)
-            .ok_or(ErrorCode::InvalidAmount)?;
+        let seeds: &[&[u8]] = &[
+            b"fee_auth_v1",
+            ctx.accounts.squad_multisig.key.as_ref(),
+            &[ctx.bumps.fee_authority_pda],
+        ];
+        let signer_seeds = &[seeds];
 
-        require!(treasury_balance >= required_zbtc_with_buffer, ErrorCode::InsufficientCollateral);
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.fee_vault.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: ctx.accounts.fee_authority_pda.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )?;
 
-        // -- 11) Emit event
-        emit!(BurnEvent {
-            user: ctx.accounts.user.key(),
-            sbtc_burned: sbtc_amount,
-            zbtc_redeemed: net_zbtc_u64,
-            fee_amount: fee_amount_u64,
-            zbtc_price_cents,
-            sbtc_price_cents,
+        emit!(FeesWithdrawnEvent {
+            squad_multisig: ctx.accounts.squad_multisig.key(),
+            destination: ctx.accounts.destination.key(),
+            amount,
+            remaining: fee_balance - amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Reallocates a `Config` written by an older program version to the current
+    /// layout and fills in fields that did not exist yet.
+    pub fn migrate_config(ctx: Context<MigrateConfig>) -> Result<()> {
+        let config_info = ctx.accounts.config.to_account_info();
+        let old_len = config_info.data_len();
+        let new_len = 8 + Config::INIT_SPACE;
+        require!(old_len >= 8 && old_len <= new_len, ErrorCode::InvalidConfigAccount);
+        require!(
+            config_info.try_borrow_data()?[..8] == *Config::DISCRIMINATOR,
+            ErrorCode::InvalidConfigAccount,
+        );
+
+        if old_len < new_len {
+            let rent_exempt = Rent::get()?.minimum_balance(new_len);
+            let top_up = rent_exempt.saturating_sub(config_info.lamports());
+            if top_up > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: ctx.accounts.squad_multisig.to_account_info(),
+                            to: config_info.clone(),
+                        },
+                    ),
+                    top_up,
+                )?;
+            }
+            config_info.realloc(new_len, true)?;
+        }
+
+        let mut config = Config::try_deserialize(&mut &config_info.try_borrow_data()?[..])?;
+        require!(config.squad_multisig == ctx.accounts.squad_multisig.key(), ErrorCode::InvalidSquadMultisig);
+
+        let old_version = config.version;
+        config.migrate();
+        config.try_serialize(&mut &mut config_info.try_borrow_mut_data()?[..])?;
+
+        emit!(ConfigMigratedEvent {
+            squad_multisig: ctx.accounts.squad_multisig.key(),
+            old_version,
+            new_version: config.version,
             timestamp: Clock::get()?.unix_timestamp,
         });
 
@@ -527,6 +776,97 @@ pub mod otc_swap {
 
 }
 
+// ========================= Helpers ================================
+/// Rejects a swap submitted with a `deadline` that `now` has passed.
+fn check_deadline(deadline: Option<i64>, now: i64) -> Result<()> {
+    if let Some(deadline) = deadline {
+        require!(now <= deadline, ErrorCode::DeadlineExceeded);
+    }
+    Ok(())
+}
+
+/// Emits `ConfigUpdatedEvent` for an admin handler that has just changed `config` from `old_params`.
+fn emit_config_updated(config: &Config, old_params: ConfigParams) -> Result<()> {
+    emit!(ConfigUpdatedEvent {
+        squad_multisig: config.squad_multisig,
+        old_params,
+        new_params: config.params(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+/// Rejects a minimum collateral ratio outside `CONFIG_MIN_COLLATERAL_BPS..=CONFIG_MAX_COLLATERAL_BPS`.
+fn check_collateral_ratio(min_collateral_bps: u64) -> Result<()> {
+    require!(
+        (CONFIG_MIN_COLLATERAL_BPS..=CONFIG_MAX_COLLATERAL_BPS).contains(&min_collateral_bps),
+        ErrorCode::InvalidCollateralRatio
+    );
+    Ok(())
+}
+
+/// zBTC (minor units) worth exactly `total_sbtc_outstanding`, i.e. 100% backing.
+fn backing_zbtc(config: &Config, zbtc_price: Price, sbtc_price: Price) -> Result<u128> {
+    // total_sbtc_outstanding * sbtc_price / zbtc_price
+    math::backing_zbtc(
+        config.total_sbtc_outstanding,
+        sbtc_price,
+        config.sbtc_decimals,
+        zbtc_price,
+        config.zbtc_decimals,
+    )
+}
+
+/// zBTC (minor units) the treasury must hold to back `total_sbtc_outstanding`
+/// at `min_collateral_bps`.
+fn required_collateral_zbtc(config: &Config, zbtc_price: Price, sbtc_price: Price) -> Result<u128> {
+    let required_zbtc_minor = backing_zbtc(config, zbtc_price, sbtc_price)?;
+
+    // Apply collateral buffer
+    math::required_collateral_zbtc(required_zbtc_minor, config.min_collateral_bps)
+}
+
+/// Collateral ratio in bps for a treasury holding `treasury_after` against `outstanding_after`
+/// sBTC, failing like the post-swap collateral check in mint.
+fn quoted_collateral_ratio(
+    config: &Config,
+    treasury_after: u64,
+    outstanding_after: u128,
+    zbtc_price: Price,
+    sbtc_price: Price,
+) -> Result<u64> {
+    let backing = math::backing_zbtc(
+        outstanding_after,
+        sbtc_price,
+        config.sbtc_decimals,
+        zbtc_price,
+        config.zbtc_decimals,
+    )?;
+    let required = math::required_collateral_zbtc(backing, config.min_collateral_bps)?;
+    require!(treasury_after as u128 >= required, ErrorCode::InsufficientCollateral);
+
+    Ok(math::collateral_ratio_bps(treasury_after, backing))
+}
+
+/// Whether the treasury is below `min_collateral_bps`, in which case a redemption pays the lesser
+/// of its oracle value and its pro-rata share of `treasury_balance`. The share is only the smaller
+/// of the two once the treasury is below 100% backing.
+fn pro_rata_mode(config: &Config, treasury_balance: u64, zbtc_price: Price, sbtc_price: Price) -> Result<bool> {
+    let required_zbtc_with_buffer = required_collateral_zbtc(config, zbtc_price, sbtc_price)?;
+    Ok((treasury_balance as u128) < required_zbtc_with_buffer)
+}
+
+/// Treasury value over outstanding sBTC value in bps; `u64::MAX` when nothing is outstanding.
+fn collateral_ratio_bps(
+    config: &Config,
+    treasury_balance: u64,
+    zbtc_price: Price,
+    sbtc_price: Price,
+) -> Result<u64> {
+    let backing_zbtc_minor = backing_zbtc(config, zbtc_price, sbtc_price)?;
+    Ok(math::collateral_ratio_bps(treasury_balance, backing_zbtc_minor))
+}
+
 // ========================= Accounts / PDAs ================================
 #[derive(Accounts)]
 pub struct Initialize<'info> {
@@ -587,7 +927,6 @@ pub struct MintSbtc<'info> {
         mut,
         seeds = [b"config_v1", squad_multisig.key().as_ref()],
         bump = config.bump,
-        has_one = authorized_zbtc_pyth_feed @ ErrorCode::InvalidOracleAccount,
         has_one = authorized_sbtc_oracle_state_pda @ ErrorCode::InvalidOracleAccount,
         constraint = config.squad_multisig == squad_multisig.key() @ ErrorCode::InvalidSquadMultisig,
     )]
@@ -649,8 +988,8 @@ pub struct MintSbtc<'info> {
     )]
     pub fee_authority_pda: UncheckedAccount<'info>,
 
-    /// CHECK: verified via has_one on config
-    pub authorized_zbtc_pyth_feed: UncheckedAccount<'info>,
+    /// CHECK: Pyth PriceUpdateV2; owner, feed id and verification level are checked in oracle::read_pyth_price
+    pub zbtc_price_update: UncheckedAccount<'info>,
 
     /// CHECK: verified via has_one on config
     pub authorized_sbtc_oracle_state_pda: UncheckedAccount<'info>,
@@ -658,27 +997,134 @@ pub struct MintSbtc<'info> {
     pub token_program: Program<'info, Token>,
 }
 
-#[derive(Accounts)]
-pub struct BurnSbtc<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-
-    /// CHECK: same squad_multisig
-    pub squad_multisig: UncheckedAccount<'info>,
-
-    #[account(
-        mut,
-        seeds = [b"config_v1", squad_multisig.key().as_ref()],
-        bump = config.bump,
-        has_one = authorized_zbtc_pyth_feed @ ErrorCode::InvalidOracleAccount,
-        has_one = authorized_sbtc_oracle_state_pda @ ErrorCode::InvalidOracleAccount,
-        constraint = config.squad_multisig == squad_multisig.key() @ ErrorCode::InvalidSquadMultisig,
-    )]
-    pub config: Box<Account<'info, Config>>,
+impl<'info> MintSbtc<'info> {
+    /// Validates the swap accounts and reads the oracles. zBTC coming in is valued at the low
+    /// end of its confidence interval. Returns `(zbtc_price, sbtc_price)`.
+    fn load_prices(&mut self, clock: &Clock) -> Result<(Price, Price)> {
+        let config = &mut self.config;
+        require!(!config.mint_paused, ErrorCode::Paused);
+        require!(self.zbtc_mint.key() == config.zbtc_mint, ErrorCode::InvalidZbtcMint);
+        require!(self.sbtc_mint.key() == config.sbtc_mint, ErrorCode::InvalidSbtcMint);
+        require!(self.user_zbtc_account.owner == self.user.key(), ErrorCode::InvalidTokenAccountOwner);
+        require!(self.user_sbtc_account.owner == self.user.key(), ErrorCode::InvalidTokenAccountOwner);
+
+        // the sBTC oracle must be no older than config.sbtc_oracle_max_age
+        let prices = PriceSnapshot::load(config, &self.zbtc_price_update, &self.authorized_sbtc_oracle_state_pda, clock)?;
+        config.accept_sbtc_price(prices.sbtc_price.to_cents(), clock.unix_timestamp)?;
+
+        Ok((prices.zbtc_bid, prices.sbtc_price))
+    }
 
-    pub zbtc_mint: Box<Account<'info, Mint>>,
+    /// Takes `zbtc_amount` from the user, of which `fee_amount` goes to the fee vault and the
+    /// rest to the treasury, mints `sbtc_out` and enforces the collateral requirement.
+    #[allow(clippy::too_many_arguments)]
+    fn settle(
+        &mut self,
+        mint_authority_bump: u8,
+        zbtc_amount: u64,
+        fee_amount: u64,
+        sbtc_out: u64,
+        zbtc_price: Price,
+        sbtc_price: Price,
+        clock: &Clock,
+    ) -> Result<()> {
+        require!(self.user_zbtc_account.amount >= zbtc_amount, ErrorCode::InsufficientBalance);
+        let net_zbtc = zbtc_amount - fee_amount;
 
-    #[account(mut)]
+        // -- Transfer zBTC to treasury and fee vault
+        token::transfer(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                Transfer {
+                    from: self.user_zbtc_account.to_account_info(),
+                    to: self.treasury_zbtc_vault.to_account_info(),
+                    authority: self.user.to_account_info(),
+                },
+            ),
+            net_zbtc,
+        )?;
+
+        if fee_amount > 0 {
+            token::transfer(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    Transfer {
+                        from: self.user_zbtc_account.to_account_info(),
+                        to: self.fee_vault.to_account_info(),
+                        authority: self.user.to_account_info(),
+                    },
+                ),
+                fee_amount,
+            )?;
+        }
+
+        // -- Mint sBTC to user
+        let seeds = &[
+            b"sbtc_mint_authority",
+            self.squad_multisig.key.as_ref(),
+            &[mint_authority_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = MintTo {
+            mint: self.sbtc_mint.to_account_info(),
+            to: self.user_sbtc_account.to_account_info(),
+            authority: self.sbtc_mint_authority_pda.to_account_info(),
+        };
+
+        let cpi_program = self.token_program.to_account_info();
+        token::mint_to(
+            CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds),
+            sbtc_out,
+        )?;
+
+        // -- Update accounting
+        let config = &mut self.config;
+        config.total_sbtc_outstanding = config.total_sbtc_outstanding
+            .checked_add(sbtc_out as u128)
+            .ok_or(ErrorCode::InvalidAmount)?;
+
+        // -- Collateral check against the post-transfer treasury balance
+        self.treasury_zbtc_vault.reload()?;
+        let treasury_balance = self.treasury_zbtc_vault.amount as u128;
+        let required_zbtc_with_buffer = required_collateral_zbtc(config, zbtc_price, sbtc_price)?;
+
+        require!(treasury_balance >= required_zbtc_with_buffer, ErrorCode::InsufficientCollateral);
+
+        emit!(MintEvent {
+            user: self.user.key(),
+            zbtc_deposited: zbtc_amount,
+            sbtc_minted: sbtc_out as u128,
+            fee_amount,
+            zbtc_price_cents: zbtc_price.to_cents(),
+            sbtc_price_cents: sbtc_price.to_cents(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct BurnSbtc<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: same squad_multisig
+    pub squad_multisig: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config_v1", squad_multisig.key().as_ref()],
+        bump = config.bump,
+        has_one = authorized_sbtc_oracle_state_pda @ ErrorCode::InvalidOracleAccount,
+        constraint = config.squad_multisig == squad_multisig.key() @ ErrorCode::InvalidSquadMultisig,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    pub zbtc_mint: Box<Account<'info, Mint>>,
+
+    #[account(mut)]
     pub sbtc_mint: Box<Account<'info, Mint>>,
 
     #[account(
@@ -725,17 +1171,330 @@ pub struct BurnSbtc<'info> {
     )]
     pub fee_authority_pda: UncheckedAccount<'info>,
 
+    /// CHECK: Pyth PriceUpdateV2; owner, feed id and verification level are checked in oracle::read_pyth_price
+    pub zbtc_price_update: UncheckedAccount<'info>,
+
     /// CHECK: verified via has_one on config
-    pub authorized_zbtc_pyth_feed: UncheckedAccount<'info>,
+    pub authorized_sbtc_oracle_state_pda: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> BurnSbtc<'info> {
+    /// Validates the swap accounts and reads the oracles. zBTC paid out is valued at the high
+    /// end of its confidence interval. Returns `(zbtc_price, sbtc_price)`.
+    fn load_prices(&mut self, clock: &Clock) -> Result<(Price, Price)> {
+        let config = &mut self.config;
+        require!(!config.burn_paused, ErrorCode::Paused);
+        require!(self.zbtc_mint.key() == config.zbtc_mint, ErrorCode::InvalidZbtcMint);
+        require!(self.sbtc_mint.key() == config.sbtc_mint, ErrorCode::InvalidSbtcMint);
+        require!(self.user_sbtc_account.owner == self.user.key(), ErrorCode::InvalidTokenAccountOwner);
+        require!(self.user_zbtc_account.owner == self.user.key(), ErrorCode::InvalidTokenAccountOwner);
+
+        let prices = PriceSnapshot::load(config, &self.zbtc_price_update, &self.authorized_sbtc_oracle_state_pda, clock)?;
+        config.accept_sbtc_price(prices.sbtc_price.to_cents(), clock.unix_timestamp)?;
+
+        Ok((prices.zbtc_ask, prices.sbtc_price))
+    }
+
+    /// Burns `sbtc_amount` from the user and pays `zbtc_out` from the treasury to the user and
+    /// `fee_amount` to the fee vault. Only requires that the treasury can pay: redemptions stay
+    /// open when the system is under-collateralised.
+    #[allow(clippy::too_many_arguments)]
+    fn settle(
+        &mut self,
+        treasury_authority_bump: u8,
+        sbtc_amount: u64,
+        fee_amount: u64,
+        zbtc_out: u64,
+        pro_rata: bool,
+        zbtc_price: Price,
+        sbtc_price: Price,
+        clock: &Clock,
+    ) -> Result<()> {
+        require!(self.user_sbtc_account.amount >= sbtc_amount, ErrorCode::InsufficientBalance);
+
+        // -- Treasury liquidity check
+        let zbtc_to_redeem = zbtc_out.checked_add(fee_amount).ok_or(ErrorCode::InvalidAmount)?;
+        require!(self.treasury_zbtc_vault.amount >= zbtc_to_redeem, ErrorCode::InsufficientLiquidity);
+
+        // -- Burn sBTC
+        token::burn(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                Burn {
+                    mint: self.sbtc_mint.to_account_info(),
+                    from: self.user_sbtc_account.to_account_info(),
+                    authority: self.user.to_account_info(),
+                },
+            ),
+            sbtc_amount,
+        )?;
+
+        // -- Transfer net redemption to user
+        let seeds: &[&[u8]] = &[
+            b"treasury_auth_v1",
+            self.squad_multisig.key.as_ref(),
+            &[treasury_authority_bump],
+        ];
+        let signer_seeds = &[seeds];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                Transfer {
+                    from: self.treasury_zbtc_vault.to_account_info(),
+                    to: self.user_zbtc_account.to_account_info(),
+                    authority: self.treasury_authority_pda.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            zbtc_out,
+        )?;
+
+        // -- Transfer fee to fee vault
+        if fee_amount > 0 {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    Transfer {
+                        from: self.treasury_zbtc_vault.to_account_info(),
+                        to: self.fee_vault.to_account_info(),
+                        authority: self.treasury_authority_pda.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                fee_amount,
+            )?;
+        }
+
+        // -- Update accounting
+        let config = &mut self.config;
+        config.total_sbtc_outstanding = config.total_sbtc_outstanding
+            .checked_sub(sbtc_amount as u128)
+            .ok_or(ErrorCode::InvalidAmount)?;
+
+        emit!(BurnEvent {
+            user: self.user.key(),
+            sbtc_burned: sbtc_amount,
+            zbtc_redeemed: zbtc_out,
+            fee_amount,
+            pro_rata,
+            zbtc_price_cents: zbtc_price.to_cents(),
+            sbtc_price_cents: sbtc_price.to_cents(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+/// Read-only accounts for pricing the treasury without moving funds.
+#[derive(Accounts)]
+pub struct TreasuryView<'info> {
+    /// CHECK: must match config.squad_multisig
+    pub squad_multisig: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"config_v1", squad_multisig.key().as_ref()],
+        bump = config.bump,
+        has_one = treasury_zbtc_vault @ ErrorCode::InvalidTreasuryVault,
+        has_one = authorized_sbtc_oracle_state_pda @ ErrorCode::InvalidOracleAccount,
+        constraint = config.squad_multisig == squad_multisig.key() @ ErrorCode::InvalidSquadMultisig,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    pub treasury_zbtc_vault: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: Pyth PriceUpdateV2; owner, feed id and verification level are checked in oracle::read_pyth_price
+    pub zbtc_price_update: UncheckedAccount<'info>,
+
+    /// CHECK: verified via has_one on config
+    pub authorized_sbtc_oracle_state_pda: UncheckedAccount<'info>,
+}
+
+impl<'info> TreasuryView<'info> {
+    /// Reads the oracles like the swaps do, checking the sBTC circuit breaker without
+    /// re-anchoring it.
+    fn load_prices(&self, clock: &Clock) -> Result<PriceSnapshot> {
+        let prices = PriceSnapshot::load(&self.config, &self.zbtc_price_update, &self.authorized_sbtc_oracle_state_pda, clock)?;
+        oracle::check_sbtc_price_change(&self.config, prices.sbtc_price.to_cents(), clock.unix_timestamp)?;
+        Ok(prices)
+    }
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub squad_multisig: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config_v1", squad_multisig.key().as_ref()],
+        bump = config.bump,
+        has_one = squad_multisig @ ErrorCode::InvalidSquadMultisig,
+    )]
+    pub config: Box<Account<'info, Config>>,
+}
+
+#[derive(Accounts)]
+pub struct Pause<'info> {
+    pub authority: Signer<'info>,
+
+    /// CHECK: must match config.squad_multisig
+    pub squad_multisig: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config_v1", squad_multisig.key().as_ref()],
+        bump = config.bump,
+        constraint = config.squad_multisig == squad_multisig.key() @ ErrorCode::InvalidSquadMultisig,
+        constraint = authority.key() == config.squad_multisig
+            || config.guardian == Some(authority.key()) @ ErrorCode::Unauthorized,
+    )]
+    pub config: Box<Account<'info, Config>>,
+}
+
+#[derive(Accounts)]
+pub struct DepositCollateral<'info> {
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    /// CHECK: must match config.squad_multisig
+    pub squad_multisig: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"config_v1", squad_multisig.key().as_ref()],
+        bump = config.bump,
+        has_one = treasury_zbtc_vault @ ErrorCode::InvalidTreasuryVault,
+        has_one = authorized_sbtc_oracle_state_pda @ ErrorCode::InvalidOracleAccount,
+        constraint = config.squad_multisig == squad_multisig.key() @ ErrorCode::InvalidSquadMultisig,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(
+        mut,
+        constraint = depositor_zbtc_account.mint == config.zbtc_mint @ ErrorCode::InvalidTokenMint,
+        constraint = depositor_zbtc_account.owner == depositor.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub depositor_zbtc_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub treasury_zbtc_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + DepositorRecord::INIT_SPACE,
+        seeds = [b"depositor_v1", config.key().as_ref(), depositor.key().as_ref()],
+        bump
+    )]
+    pub depositor_record: Box<Account<'info, DepositorRecord>>,
+
+    /// CHECK: Pyth PriceUpdateV2; owner, feed id and verification level are checked in oracle::read_pyth_price
+    pub zbtc_price_update: UncheckedAccount<'info>,
 
     /// CHECK: verified via has_one on config
     pub authorized_sbtc_oracle_state_pda: UncheckedAccount<'info>,
 
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawExcessCollateral<'info> {
+    pub squad_multisig: Signer<'info>,
+
+    #[account(
+        seeds = [b"config_v1", squad_multisig.key().as_ref()],
+        bump = config.bump,
+        has_one = squad_multisig @ ErrorCode::InvalidSquadMultisig,
+        has_one = treasury_zbtc_vault @ ErrorCode::InvalidTreasuryVault,
+        has_one = authorized_sbtc_oracle_state_pda @ ErrorCode::InvalidOracleAccount,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(
+        mut,
+        token::authority = treasury_authority_pda,
+    )]
+    pub treasury_zbtc_vault: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: PDA used as authority for treasury token account
+    #[account(
+        seeds = [b"treasury_auth_v1", squad_multisig.key().as_ref()],
+        bump,
+    )]
+    pub treasury_authority_pda: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = destination.mint == config.zbtc_mint @ ErrorCode::InvalidTokenMint,
+    )]
+    pub destination: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: Pyth PriceUpdateV2; owner, feed id and verification level are checked in oracle::read_pyth_price
+    pub zbtc_price_update: UncheckedAccount<'info>,
+
+    /// CHECK: verified via has_one on config
+    pub authorized_sbtc_oracle_state_pda: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    pub squad_multisig: Signer<'info>,
+
+    #[account(
+        seeds = [b"config_v1", squad_multisig.key().as_ref()],
+        bump = config.bump,
+        has_one = squad_multisig @ ErrorCode::InvalidSquadMultisig,
+        has_one = fee_vault @ ErrorCode::InvalidFeeVault,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(
+        mut,
+        token::authority = fee_authority_pda,
+    )]
+    pub fee_vault: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: PDA used as authority for fee token account
+    #[account(
+        seeds = [b"fee_auth_v1", squad_multisig.key().as_ref()],
+        bump,
+    )]
+    pub fee_authority_pda: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = destination.mint == config.zbtc_mint @ ErrorCode::InvalidTokenMint,
+    )]
+    pub destination: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    #[account(mut)]
+    pub squad_multisig: Signer<'info>,
+
+    /// CHECK: legacy layouts are shorter than Config and cannot be deserialized
+    /// until reallocated; discriminator and squad_multisig are checked in the handler
+    #[account(
+        mut,
+        owner = crate::ID @ ErrorCode::InvalidConfigAccount,
+        seeds = [b"config_v1", squad_multisig.key().as_ref()],
+        bump,
+    )]
+    pub config: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[account]
-#[derive(InitSpace)]
+#[derive(InitSpace, Default)]
 pub struct Config {
     pub squad_multisig: Pubkey,
     pub sbtc_mint: Pubkey,
@@ -747,11 +1506,140 @@ pub struct Config {
     pub bump: u8,
     pub sbtc_decimals: u8,
     pub zbtc_decimals: u8,
-    pub paused: bool,
+    /// Halts mint_sbtc. Occupies the byte of the v0 `paused` flag.
+    pub mint_paused: bool,
     pub total_sbtc_outstanding: u128,
     pub created_at: i64,
-    pub authorized_zbtc_pyth_feed: Pubkey,
+    /// Pyth price feed id for zBTC/USD, matched against PriceUpdateV2 accounts.
+    /// Occupies the bytes of the v2 `authorized_zbtc_pyth_feed` account key.
+    pub zbtc_pyth_feed_id: [u8; 32],
     pub authorized_sbtc_oracle_state_pda: Pubkey,
+    /// Optional key allowed to pause (but not unpause) the protocol
+    pub guardian: Option<Pubkey>,
+    /// Halts burn_sbtc independently of mint_sbtc
+    pub burn_paused: bool,
+    /// Layout version, see `Config::migrate`
+    pub version: u8,
+    /// Max age in seconds of the sBTC oracle `last_update`
+    pub sbtc_oracle_max_age: u64,
+    /// Program that must own `authorized_sbtc_oracle_state_pda`
+    pub sbtc_oracle_program_id: Pubkey,
+    /// Fresh publisher submissions required to price sBTC
+    pub sbtc_oracle_min_publishers: u8,
+    /// Max spread between the highest and lowest counted submission, in bps of the median
+    pub sbtc_oracle_max_spread_bps: u64,
+    /// Max sBTC price move per `sbtc_price_change_window`, in bps of the anchored price
+    pub sbtc_max_price_change_bps: u64,
+    /// Seconds per circuit breaker window
+    pub sbtc_price_change_window: u64,
+    /// sBTC price the circuit breaker measures against; 0 until the first mint/burn
+    pub last_sbtc_price_cents: u64,
+    pub last_sbtc_price_at: i64,
+    /// Max zBTC confidence interval, in bps of the price
+    pub max_conf_bps: u64,
+    /// Max distance of the spot zBTC price from its EMA, in bps of the EMA
+    pub max_ema_deviation_bps: u64,
+}
+
+impl Config {
+    /// Snapshot of the parameters the squad multisig can change after initialize.
+    pub fn params(&self) -> ConfigParams {
+        ConfigParams {
+            fee_rate_bps: self.fee_rate_bps,
+            min_collateral_bps: self.min_collateral_bps,
+            zbtc_pyth_feed_id: self.zbtc_pyth_feed_id,
+            authorized_sbtc_oracle_state_pda: self.authorized_sbtc_oracle_state_pda,
+            guardian: self.guardian,
+            sbtc_oracle_max_age: self.sbtc_oracle_max_age,
+            sbtc_oracle_program_id: self.sbtc_oracle_program_id,
+            sbtc_oracle_min_publishers: self.sbtc_oracle_min_publishers,
+            sbtc_oracle_max_spread_bps: self.sbtc_oracle_max_spread_bps,
+            sbtc_max_price_change_bps: self.sbtc_max_price_change_bps,
+            sbtc_price_change_window: self.sbtc_price_change_window,
+            max_conf_bps: self.max_conf_bps,
+            max_ema_deviation_bps: self.max_ema_deviation_bps,
+        }
+    }
+
+    /// Runs the sBTC price circuit breaker, re-anchoring once a full window has passed.
+    pub fn accept_sbtc_price(&mut self, price: u64, now: i64) -> Result<()> {
+        oracle::check_sbtc_price_change(self, price, now)?;
+
+        let elapsed = now.saturating_sub(self.last_sbtc_price_at);
+        if self.last_sbtc_price_cents == 0 || elapsed >= self.sbtc_price_change_window as i64 {
+            self.last_sbtc_price_cents = price;
+            self.last_sbtc_price_at = now;
+        }
+
+        Ok(())
+    }
+
+    /// Brings an account written by an older program version up to `CONFIG_VERSION`.
+    /// Fields appended since that version read as zero after realloc.
+    fn migrate(&mut self) {
+        if self.version < 1 {
+            // v0 had a single `paused` flag covering both directions
+            self.burn_paused = self.mint_paused;
+        }
+        if self.version < 2 {
+            self.sbtc_oracle_max_age = ORACLE_MAX_AGE;
+        }
+        if self.version < 3 {
+            // held a legacy push-oracle account key, not a feed id; must be set via update_oracles
+            self.zbtc_pyth_feed_id = [0u8; 32];
+        }
+        if self.version < 4 {
+            self.sbtc_oracle_program_id = sbtc_oracle::ID;
+        }
+        if self.version < 5 {
+            self.sbtc_oracle_min_publishers = SBTC_ORACLE_MIN_PUBLISHERS;
+            self.sbtc_oracle_max_spread_bps = SBTC_ORACLE_MAX_SPREAD_BPS;
+        }
+        if self.version < 6 {
+            self.sbtc_max_price_change_bps = SBTC_MAX_PRICE_CHANGE_BPS;
+            self.sbtc_price_change_window = SBTC_PRICE_CHANGE_WINDOW;
+        }
+        if self.version < 7 {
+            // matches the previous hard-coded `conf < price / 1000`
+            self.max_conf_bps = ZBTC_MAX_CONF_BPS;
+        }
+        if self.version < 8 {
+            self.max_ema_deviation_bps = ZBTC_MAX_EMA_DEVIATION_BPS;
+        }
+        if self.version < 9 {
+            // the v5 default of a single publisher let one submission set the price
+            self.sbtc_oracle_min_publishers = self.sbtc_oracle_min_publishers.max(SBTC_ORACLE_MIN_PUBLISHERS);
+        }
+        self.version = CONFIG_VERSION;
+    }
+}
+
+/// Cumulative treasury contributions made through `deposit_collateral`.
+#[account]
+#[derive(InitSpace)]
+pub struct DepositorRecord {
+    pub depositor: Pubkey,
+    pub config: Pubkey,
+    pub total_deposited: u64,
+    pub last_deposit_at: i64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConfigParams {
+    pub fee_rate_bps: u64,
+    pub min_collateral_bps: u64,
+    pub zbtc_pyth_feed_id: [u8; 32],
+    pub authorized_sbtc_oracle_state_pda: Pubkey,
+    pub guardian: Option<Pubkey>,
+    pub sbtc_oracle_max_age: u64,
+    pub sbtc_oracle_program_id: Pubkey,
+    pub sbtc_oracle_min_publishers: u8,
+    pub sbtc_oracle_max_spread_bps: u64,
+    pub sbtc_max_price_change_bps: u64,
+    pub sbtc_price_change_window: u64,
+    pub max_conf_bps: u64,
+    pub max_ema_deviation_bps: u64,
 }
 
 // ========================= Events ================================
@@ -768,10 +1656,46 @@ pub struct InitializedEvent {
     pub sbtc_mint_authority: Pubkey,
     pub treasury_vault_authority: Pubkey,
     pub fee_vault_authority: Pubkey,
-    pub authorized_zbtc_pyth_feed: Pubkey,
+    pub zbtc_pyth_feed_id: [u8; 32],
     pub authorized_sbtc_oracle_state_pda: Pubkey,
 }
 
+/// Swap preview returned by `quote_mint` and `quote_burn`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quote {
+    /// sBTC minted, or net zBTC paid out, in minor units
+    pub output: u64,
+    /// Fee in zBTC minor units
+    pub fee: u64,
+    pub zbtc_price_cents: u64,
+    pub sbtc_price_cents: u64,
+    /// Treasury collateral ratio in bps after the swap; `u64::MAX` with nothing outstanding
+    pub collateral_ratio_after: u64,
+}
+
+/// Treasury solvency returned by `get_system_health`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SystemHealth {
+    pub treasury_balance: u64,
+    pub total_sbtc_outstanding: u128,
+    /// zBTC the treasury must hold at `min_collateral_bps`
+    pub required_collateral: u128,
+    /// `u64::MAX` with nothing outstanding
+    pub collateral_ratio_bps: u64,
+}
+
+#[event]
+pub struct HealthEvent {
+    pub treasury_balance: u64,
+    pub total_sbtc_outstanding: u128,
+    pub required_collateral: u128,
+    pub collateral_ratio_bps: u64,
+    pub min_collateral_bps: u64,
+    pub zbtc_price_cents: u64,
+    pub sbtc_price_cents: u64,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct MintEvent {
     pub user: Pubkey,
@@ -789,16 +1713,76 @@ pub struct BurnEvent {
     pub sbtc_burned: u64,
     pub zbtc_redeemed: u64,
     pub fee_amount: u64,
+    /// Paid the pro-rata share of the treasury because it was less than the oracle value
+    pub pro_rata: bool,
     pub zbtc_price_cents: u64,
     pub sbtc_price_cents: u64,
     pub timestamp: i64,
 }
+
+#[event]
+pub struct ConfigUpdatedEvent {
+    pub squad_multisig: Pubkey,
+    pub old_params: ConfigParams,
+    pub new_params: ConfigParams,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PauseEvent {
+    pub authority: Pubkey,
+    pub mint_paused: bool,
+    pub burn_paused: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CollateralDepositedEvent {
+    pub depositor: Pubkey,
+    pub amount: u64,
+    pub total_deposited: u64,
+    pub treasury_balance: u64,
+    /// `None` when the oracles could not be read; the deposit goes through regardless
+    pub collateral_ratio_bps: Option<u64>,
+    pub zbtc_price_cents: Option<u64>,
+    pub sbtc_price_cents: Option<u64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ExcessCollateralWithdrawnEvent {
+    pub squad_multisig: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub treasury_balance: u64,
+    pub required_collateral: u64,
+    pub zbtc_price_cents: u64,
+    pub sbtc_price_cents: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeesWithdrawnEvent {
+    pub squad_multisig: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub remaining: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ConfigMigratedEvent {
+    pub squad_multisig: Pubkey,
+    pub old_version: u8,
+    pub new_version: u8,
+    pub timestamp: i64,
+}
 // ========================= Errors ================================
 #[error_code]
 pub enum ErrorCode {
     #[msg("Fee rate must be 5% or less")]
     InvalidFeeRate,
-    #[msg("Collateral ratio must be at least 10%")]
+    #[msg("Collateral ratio must be between 200% and 1000%")]
     InvalidCollateralRatio,
     #[msg("sBTC mint must have Squad as initial authority")]
     InvalidMintAuthority,
@@ -844,4 +1828,56 @@ pub enum ErrorCode {
     InvalidPrice,
     #[msg("High confidence interval - unreliable data")]
     HighConfidence,
+    #[msg("Signer is not authorized for this action")]
+    Unauthorized,
+    #[msg("Invalid config account")]
+    InvalidConfigAccount,
+    #[msg("Oracle max age must be positive")]
+    InvalidOracleMaxAge,
+    #[msg("Oracle timestamp is in the future")]
+    InvalidOracleTimestamp,
+    #[msg("Price update does not match the configured Pyth feed id")]
+    InvalidPythFeedId,
+    #[msg("Price update is not fully verified")]
+    InsufficientVerificationLevel,
+    #[msg("Not enough fresh sBTC oracle submissions")]
+    InsufficientOracleQuorum,
+    #[msg("sBTC oracle submissions disagree beyond the max spread")]
+    OracleSpreadTooWide,
+    #[msg("Oracle quorum must be between the quorum floor and the max publisher count, spread at most 10000 bps")]
+    InvalidOracleQuorum,
+    #[msg("sBTC price moved faster than the circuit breaker allows")]
+    SbtcPriceChangeTooFast,
+    #[msg("Price breaker threshold and window must be positive")]
+    InvalidPriceBreaker,
+    #[msg("Max confidence must be between 1 and 9999 bps")]
+    InvalidMaxConf,
+    #[msg("Spot zBTC price deviates too far from its EMA")]
+    EmaDeviationTooHigh,
+    #[msg("EMA band must be between 1 and 10000 bps")]
+    InvalidEmaBand,
+    #[msg("Swap output is below the requested minimum")]
+    SlippageExceeded,
+    #[msg("Swap deadline has passed")]
+    DeadlineExceeded,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collateral_ratio_must_be_within_bounds() {
+        assert!(check_collateral_ratio(CONFIG_MIN_COLLATERAL_BPS).is_ok());
+        assert!(check_collateral_ratio(CONFIG_MAX_COLLATERAL_BPS).is_ok());
+        assert_eq!(
+            check_collateral_ratio(CONFIG_MIN_COLLATERAL_BPS - 1).unwrap_err(),
+            ErrorCode::InvalidCollateralRatio.into()
+        );
+        assert_eq!(
+            check_collateral_ratio(CONFIG_MAX_COLLATERAL_BPS + 1).unwrap_err(),
+            ErrorCode::InvalidCollateralRatio.into()
+        );
+        assert_eq!(check_collateral_ratio(u64::MAX).unwrap_err(), ErrorCode::InvalidCollateralRatio.into());
+    }
 }
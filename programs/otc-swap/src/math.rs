@@ -0,0 +1,396 @@
+// Pure swap and collateral computations used by the instruction handlers.
+
+use anchor_lang::prelude::*;
+
+use crate::price::{self, Price};
+use crate::ErrorCode;
+
+const BPS_DENOMINATOR: u128 = 10_000;
+
+/// Splits `amount` into `(fee, net)` at `fee_rate_bps`, rounding the fee down.
+pub fn split_fee(amount: u64, fee_rate_bps: u64) -> Result<(u64, u64)> {
+    require!(fee_rate_bps as u128 <= BPS_DENOMINATOR, ErrorCode::InvalidFeeRate);
+    // fee <= amount, so it fits in u64 and the subtraction cannot underflow
+    let fee = (amount as u128 * fee_rate_bps as u128 / BPS_DENOMINATOR) as u64;
+    Ok((fee, amount - fee))
+}
+
+/// Smallest `amount` whose `split_fee` net is at least `net`.
+pub fn gross_for_net(net: u64, fee_rate_bps: u64) -> Result<u64> {
+    require!((fee_rate_bps as u128) < BPS_DENOMINATOR, ErrorCode::InvalidFeeRate);
+    if net == 0 {
+        return Ok(0);
+    }
+    // amount - floor(amount * fee / 10_000) >= net  <=>  amount * (10_000 - fee) > 10_000 * (net - 1)
+    let gross = (net as u128 - 1) * BPS_DENOMINATOR / (BPS_DENOMINATOR - fee_rate_bps as u128) + 1;
+    let gross = u64::try_from(gross).map_err(|_| ErrorCode::InvalidAmount)?;
+    Ok(gross)
+}
+
+/// sBTC (minor units) minted for `net_zbtc` deposited zBTC. Zero or more than `u64::MAX` is
+/// rejected.
+pub fn sbtc_to_mint(
+    net_zbtc: u64,
+    zbtc_price: Price,
+    zbtc_decimals: u8,
+    sbtc_price: Price,
+    sbtc_decimals: u8,
+) -> Result<u64> {
+    let sbtc = price::convert(net_zbtc as u128, zbtc_price, zbtc_decimals, sbtc_price, sbtc_decimals)?;
+    to_nonzero_u64(sbtc)
+}
+
+/// zBTC (minor units), before fees, redeemed for `sbtc_amount` burned sBTC. Zero or more than
+/// `u64::MAX` is rejected.
+pub fn zbtc_to_redeem(
+    sbtc_amount: u64,
+    sbtc_price: Price,
+    sbtc_decimals: u8,
+    zbtc_price: Price,
+    zbtc_decimals: u8,
+) -> Result<u64> {
+    let zbtc = price::convert(sbtc_amount as u128, sbtc_price, sbtc_decimals, zbtc_price, zbtc_decimals)?;
+    to_nonzero_u64(zbtc)
+}
+
+/// Net zBTC (minor units) to deposit so that `sbtc_to_mint` yields at least `sbtc_out`.
+/// Rounds up, in the protocol's favour.
+pub fn zbtc_for_sbtc_out(
+    sbtc_out: u64,
+    zbtc_price: Price,
+    zbtc_decimals: u8,
+    sbtc_price: Price,
+    sbtc_decimals: u8,
+) -> Result<u64> {
+    let zbtc = price::convert_ceil(sbtc_out as u128, sbtc_price, sbtc_decimals, zbtc_price, zbtc_decimals)?;
+    to_nonzero_u64(zbtc)
+}
+
+/// sBTC (minor units) to burn so that `zbtc_to_redeem` yields at least `zbtc_out`.
+/// Rounds up, in the protocol's favour.
+pub fn sbtc_for_zbtc_out(
+    zbtc_out: u64,
+    sbtc_price: Price,
+    sbtc_decimals: u8,
+    zbtc_price: Price,
+    zbtc_decimals: u8,
+) -> Result<u64> {
+    let sbtc = price::convert_ceil(zbtc_out as u128, zbtc_price, zbtc_decimals, sbtc_price, sbtc_decimals)?;
+    to_nonzero_u64(sbtc)
+}
+
+/// zBTC (minor units) worth exactly `total_sbtc_outstanding`, i.e. 100% backing.
+pub fn backing_zbtc(
+    total_sbtc_outstanding: u128,
+    sbtc_price: Price,
+    sbtc_decimals: u8,
+    zbtc_price: Price,
+    zbtc_decimals: u8,
+) -> Result<u128> {
+    price::convert(total_sbtc_outstanding, sbtc_price, sbtc_decimals, zbtc_price, zbtc_decimals)
+}
+
+/// `backing_zbtc` scaled by the `min_collateral_bps` buffer.
+pub fn required_collateral_zbtc(backing_zbtc: u128, min_collateral_bps: u64) -> Result<u128> {
+    let required = backing_zbtc
+        .checked_mul(min_collateral_bps as u128)
+        .ok_or(ErrorCode::InvalidAmount)?
+        / BPS_DENOMINATOR;
+    Ok(required)
+}
+
+/// `treasury_balance` over `backing_zbtc` in bps, saturating at `u64::MAX`; `u64::MAX` when
+/// nothing is outstanding.
+pub fn collateral_ratio_bps(treasury_balance: u64, backing_zbtc: u128) -> u64 {
+    if backing_zbtc == 0 {
+        return u64::MAX;
+    }
+    // u64 * 10_000 cannot overflow u128
+    let ratio_bps = treasury_balance as u128 * BPS_DENOMINATOR / backing_zbtc;
+    u64::try_from(ratio_bps).unwrap_or(u64::MAX)
+}
+
+/// `treasury_balance` shared pro rata across `total_sbtc_outstanding`: the zBTC (minor units)
+/// owed to `sbtc_amount` burned sBTC. Rounds down.
+pub fn pro_rata_zbtc(sbtc_amount: u64, treasury_balance: u64, total_sbtc_outstanding: u128) -> Result<u64> {
+    require!(total_sbtc_outstanding > 0, ErrorCode::InvalidAmount);
+    // u64 * u64 cannot overflow u128
+    let share = sbtc_amount as u128 * treasury_balance as u128 / total_sbtc_outstanding;
+    let share = u64::try_from(share).map_err(|_| ErrorCode::InvalidAmount)?;
+    Ok(share)
+}
+
+/// sBTC (minor units) to burn so that `pro_rata_zbtc` yields at least `zbtc_out`.
+/// Rounds up, in the protocol's favour.
+pub fn sbtc_for_pro_rata_out(zbtc_out: u64, treasury_balance: u64, total_sbtc_outstanding: u128) -> Result<u64> {
+    require!(treasury_balance > 0, ErrorCode::InsufficientLiquidity);
+    let sbtc = (zbtc_out as u128)
+        .checked_mul(total_sbtc_outstanding)
+        .ok_or(ErrorCode::InvalidAmount)?
+        .div_ceil(treasury_balance as u128);
+    to_nonzero_u64(sbtc)
+}
+
+fn to_nonzero_u64(amount: u128) -> Result<u64> {
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    let amount = u64::try_from(amount).map_err(|_| ErrorCode::InvalidAmount)?;
+    Ok(amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CONFIG_MAX_COLLATERAL_BPS, CONFIG_MAX_FEE_RATE_BPS, CONFIG_MIN_COLLATERAL_BPS};
+    use proptest::prelude::*;
+
+    fn usd(dollars: u64) -> Price {
+        Price::from_cents(dollars * 100)
+    }
+
+    /// Amounts biased towards the u64 boundaries
+    fn amount() -> impl Strategy<Value = u64> {
+        prop_oneof![
+            Just(0),
+            Just(1),
+            Just(u64::MAX - 1),
+            Just(u64::MAX),
+            0u64..=1_000,
+            any::<u64>(),
+        ]
+    }
+
+    /// Prices from 1e-12 USD up to beyond any realistic quote
+    fn price() -> impl Strategy<Value = Price> {
+        prop_oneof![
+            Just(Price::from_raw(1)),
+            Just(Price::from_raw(u64::MAX as u128)),
+            Just(Price::from_raw(u128::MAX)),
+            (1u128..=u128::MAX).prop_map(Price::from_raw),
+            (1u64..=10_000_000).prop_map(usd),
+        ]
+    }
+
+    #[test]
+    fn split_fee_rounds_fee_down() {
+        assert_eq!(split_fee(1_000_000, 30).unwrap(), (3_000, 997_000));
+        assert_eq!(split_fee(333, 30).unwrap(), (0, 333));
+        assert_eq!(split_fee(0, 30).unwrap(), (0, 0));
+        assert_eq!(split_fee(u64::MAX, 10_000).unwrap(), (u64::MAX, 0));
+        assert_eq!(split_fee(u64::MAX, 0).unwrap(), (0, u64::MAX));
+        assert_eq!(split_fee(1, 10_001).unwrap_err(), ErrorCode::InvalidFeeRate.into());
+    }
+
+    #[test]
+    fn sbtc_to_mint_at_equal_prices_is_decimal_rescale() {
+        assert_eq!(sbtc_to_mint(100_000_000, usd(100_000), 8, usd(100_000), 8).unwrap(), 100_000_000);
+        assert_eq!(sbtc_to_mint(100_000_000, usd(100_000), 8, usd(100_000), 6).unwrap(), 1_000_000);
+        assert_eq!(sbtc_to_mint(1, usd(100_000), 0, usd(100_000), 18).unwrap(), 10u64.pow(18));
+        // 0.5 sBTC at 6 decimals for 1 zBTC at half the price
+        assert_eq!(sbtc_to_mint(100_000_000, usd(50_000), 8, usd(100_000), 6).unwrap(), 500_000);
+    }
+
+    #[test]
+    fn sbtc_to_mint_rejects_dust_and_u64_overflow() {
+        assert_eq!(sbtc_to_mint(1, usd(1), 8, usd(100_000), 8).unwrap_err(), ErrorCode::InvalidAmount.into());
+        assert_eq!(
+            sbtc_to_mint(u64::MAX, usd(100_000), 8, usd(1), 8).unwrap_err(),
+            ErrorCode::InvalidAmount.into()
+        );
+        assert_eq!(
+            sbtc_to_mint(u64::MAX, Price::from_raw(u128::MAX), 0, usd(1), 0).unwrap_err(),
+            ErrorCode::InvalidAmount.into()
+        );
+    }
+
+    #[test]
+    fn zbtc_to_redeem_inverts_mint_at_equal_prices() {
+        let minted = sbtc_to_mint(123_456_789, usd(100_000), 8, usd(100_000), 9).unwrap();
+        assert_eq!(zbtc_to_redeem(minted, usd(100_000), 9, usd(100_000), 8).unwrap(), 123_456_789);
+        assert_eq!(zbtc_to_redeem(1, usd(1), 18, usd(100_000), 0).unwrap_err(), ErrorCode::InvalidAmount.into());
+    }
+
+    #[test]
+    fn gross_for_net_is_minimal() {
+        assert_eq!(gross_for_net(0, 30).unwrap(), 0);
+        // A 1 unit net pays no fee since the fee rounds down
+        assert_eq!(gross_for_net(1, 30).unwrap(), 1);
+        assert_eq!(gross_for_net(9_970, 30).unwrap(), 9_999);
+        assert_eq!(split_fee(9_999, 30).unwrap(), (29, 9_970));
+        assert_eq!(gross_for_net(997_000, 30).unwrap(), 999_999);
+        assert_eq!(gross_for_net(u64::MAX, 0).unwrap(), u64::MAX);
+        assert_eq!(gross_for_net(u64::MAX, 30).unwrap_err(), ErrorCode::InvalidAmount.into());
+        assert_eq!(gross_for_net(1, 10_000).unwrap_err(), ErrorCode::InvalidFeeRate.into());
+    }
+
+    #[test]
+    fn exact_out_inverts_mint_and_burn() {
+        let zbtc_in = zbtc_for_sbtc_out(100_000_000, usd(100_000), 8, usd(95_000), 8).unwrap();
+        assert_eq!(zbtc_in, 95_000_000);
+        assert_eq!(sbtc_to_mint(zbtc_in, usd(100_000), 8, usd(95_000), 8).unwrap(), 100_000_000);
+
+        // 1 sBTC at $3 needs ceil(1e8 * 3 / 7) zBTC at $7
+        let zbtc_in = zbtc_for_sbtc_out(100_000_000, usd(7), 8, usd(3), 8).unwrap();
+        assert_eq!(zbtc_in, 42_857_143);
+        assert_eq!(sbtc_to_mint(zbtc_in - 1, usd(7), 8, usd(3), 8).unwrap(), 99_999_998);
+
+        let sbtc_in = sbtc_for_zbtc_out(100_000_000, usd(3), 6, usd(7), 8).unwrap();
+        assert_eq!(sbtc_in, 2_333_334);
+        assert!(zbtc_to_redeem(sbtc_in, usd(3), 6, usd(7), 8).unwrap() >= 100_000_000);
+    }
+
+    #[test]
+    fn required_collateral_applies_buffer() {
+        let backing = backing_zbtc(100_000_000, usd(100_000), 8, usd(50_000), 8).unwrap();
+        assert_eq!(backing, 200_000_000);
+        assert_eq!(required_collateral_zbtc(backing, CONFIG_MIN_COLLATERAL_BPS).unwrap(), 400_000_000);
+        assert_eq!(required_collateral_zbtc(0, CONFIG_MIN_COLLATERAL_BPS).unwrap(), 0);
+        assert_eq!(
+            required_collateral_zbtc(u128::MAX, CONFIG_MIN_COLLATERAL_BPS).unwrap_err(),
+            ErrorCode::InvalidAmount.into()
+        );
+    }
+
+    #[test]
+    fn collateral_ratio_handles_empty_and_huge_treasuries() {
+        assert_eq!(collateral_ratio_bps(400_000_000, 200_000_000), 20_000);
+        assert_eq!(collateral_ratio_bps(0, 200_000_000), 0);
+        assert_eq!(collateral_ratio_bps(1, 0), u64::MAX);
+        assert_eq!(collateral_ratio_bps(u64::MAX, 1), u64::MAX);
+    }
+
+    #[test]
+    fn pro_rata_shares_the_treasury() {
+        // Half the supply of a 1.5 zBTC treasury gets 0.75 zBTC
+        assert_eq!(pro_rata_zbtc(50_000_000, 150_000_000, 100_000_000).unwrap(), 75_000_000);
+        assert_eq!(pro_rata_zbtc(1, 2, 3).unwrap(), 0);
+        assert_eq!(pro_rata_zbtc(u64::MAX, u64::MAX, u64::MAX as u128).unwrap(), u64::MAX);
+        assert_eq!(pro_rata_zbtc(1, 1, 0).unwrap_err(), ErrorCode::InvalidAmount.into());
+
+        assert_eq!(sbtc_for_pro_rata_out(75_000_000, 150_000_000, 100_000_000).unwrap(), 50_000_000);
+        assert_eq!(sbtc_for_pro_rata_out(1, 3, 2).unwrap(), 1);
+        assert_eq!(sbtc_for_pro_rata_out(1, 0, 2).unwrap_err(), ErrorCode::InsufficientLiquidity.into());
+    }
+
+    proptest! {
+        #[test]
+        fn split_fee_conserves_amount(amount in amount(), fee_rate_bps in 0u64..=10_000) {
+            let (fee, net) = split_fee(amount, fee_rate_bps).unwrap();
+            prop_assert_eq!(fee as u128 + net as u128, amount as u128);
+            prop_assert!(fee as u128 * 10_000 <= amount as u128 * fee_rate_bps as u128);
+        }
+
+        #[test]
+        fn mint_then_burn_never_returns_more_zbtc(
+            zbtc_amount in amount(),
+            fee_rate_bps in 0u64..=CONFIG_MAX_FEE_RATE_BPS,
+            bid in price(),
+            ask_premium in 0u128..=1_000_000,
+            sbtc_price in price(),
+            zbtc_decimals in 0u8..=18,
+            sbtc_decimals in 0u8..=18,
+        ) {
+            let ask = Price::from_raw(bid.raw().saturating_add(ask_premium));
+            let (_, net_zbtc) = split_fee(zbtc_amount, fee_rate_bps).unwrap();
+
+            // Overflow and dust are surfaced as errors, never as panics
+            let Ok(minted) = sbtc_to_mint(net_zbtc, bid, zbtc_decimals, sbtc_price, sbtc_decimals) else {
+                return Ok(());
+            };
+            let Ok(redeemed) = zbtc_to_redeem(minted, sbtc_price, sbtc_decimals, ask, zbtc_decimals) else {
+                return Ok(());
+            };
+            let (_, net_redeemed) = split_fee(redeemed, fee_rate_bps).unwrap();
+
+            prop_assert!(redeemed <= net_zbtc);
+            prop_assert!(net_redeemed <= zbtc_amount);
+        }
+
+        #[test]
+        fn gross_for_net_is_the_smallest_sufficient_amount(
+            net in amount(),
+            fee_rate_bps in 0u64..=CONFIG_MAX_FEE_RATE_BPS,
+        ) {
+            let Ok(gross) = gross_for_net(net, fee_rate_bps) else {
+                return Ok(());
+            };
+            prop_assert!(split_fee(gross, fee_rate_bps).unwrap().1 >= net);
+            if gross > 0 {
+                prop_assert!(split_fee(gross - 1, fee_rate_bps).unwrap().1 < net);
+            }
+        }
+
+        #[test]
+        fn exact_out_inputs_always_reach_the_output(
+            out in amount(),
+            zbtc_price in price(),
+            sbtc_price in price(),
+            zbtc_decimals in 0u8..=18,
+            sbtc_decimals in 0u8..=18,
+        ) {
+            if let Ok(zbtc_in) = zbtc_for_sbtc_out(out, zbtc_price, zbtc_decimals, sbtc_price, sbtc_decimals) {
+                if let Ok(minted) = sbtc_to_mint(zbtc_in, zbtc_price, zbtc_decimals, sbtc_price, sbtc_decimals) {
+                    prop_assert!(minted >= out);
+                }
+            }
+            if let Ok(sbtc_in) = sbtc_for_zbtc_out(out, sbtc_price, sbtc_decimals, zbtc_price, zbtc_decimals) {
+                if let Ok(redeemed) = zbtc_to_redeem(sbtc_in, sbtc_price, sbtc_decimals, zbtc_price, zbtc_decimals) {
+                    prop_assert!(redeemed >= out);
+                }
+            }
+        }
+
+        #[test]
+        fn pro_rata_never_pays_out_more_than_the_treasury(
+            sbtc_amount in amount(),
+            treasury_balance in amount(),
+            outstanding in 1u128..=u128::MAX,
+        ) {
+            prop_assume!(sbtc_amount as u128 <= outstanding);
+            let share = pro_rata_zbtc(sbtc_amount, treasury_balance, outstanding).unwrap();
+            prop_assert!(share <= treasury_balance);
+        }
+
+        #[test]
+        fn pro_rata_exact_out_inputs_always_reach_the_output(
+            out in amount(),
+            treasury_balance in amount(),
+            outstanding in 1u128..=u64::MAX as u128,
+        ) {
+            if let Ok(sbtc_in) = sbtc_for_pro_rata_out(out, treasury_balance, outstanding) {
+                if let Ok(share) = pro_rata_zbtc(sbtc_in, treasury_balance, outstanding) {
+                    prop_assert!(share >= out);
+                }
+            }
+        }
+
+        #[test]
+        fn required_collateral_covers_backing(
+            outstanding in amount(),
+            sbtc_price in price(),
+            zbtc_price in price(),
+            sbtc_decimals in 0u8..=18,
+            zbtc_decimals in 0u8..=18,
+            min_collateral_bps in CONFIG_MIN_COLLATERAL_BPS..=CONFIG_MAX_COLLATERAL_BPS,
+        ) {
+            let Ok(backing) = backing_zbtc(outstanding as u128, sbtc_price, sbtc_decimals, zbtc_price, zbtc_decimals)
+            else {
+                return Ok(());
+            };
+            if let Ok(required) = required_collateral_zbtc(backing, min_collateral_bps) {
+                prop_assert!(required >= backing);
+            }
+        }
+
+        #[test]
+        fn ratio_at_minimum_implies_required_collateral_held(
+            treasury_balance in amount(),
+            backing in 1u128..=u64::MAX as u128,
+            min_collateral_bps in CONFIG_MIN_COLLATERAL_BPS..=CONFIG_MAX_COLLATERAL_BPS,
+        ) {
+            let required = required_collateral_zbtc(backing, min_collateral_bps).unwrap();
+            if collateral_ratio_bps(treasury_balance, backing) >= min_collateral_bps {
+                prop_assert!(treasury_balance as u128 >= required);
+            }
+        }
+    }
+}
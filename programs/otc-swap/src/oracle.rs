@@ -0,0 +1,652 @@
+// Price loading shared by every instruction that reads the zBTC/USD and sBTC/USD oracles.
+
+use anchor_lang::prelude::*;
+use pyth_solana_receiver_sdk::error::GetPriceError;
+use pyth_solana_receiver_sdk::price_update::{PriceUpdateV2, VerificationLevel};
+use sbtc_oracle::{OracleState, Submission};
+
+use crate::price::Price;
+use crate::{Config, ErrorCode, ORACLE_MAX_AGE};
+
+/// Raw zBTC/USD reading, as posted by the Pyth receiver or mock-pyth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PythPrice {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_time: i64,
+    pub ema_price: i64,
+    pub ema_expo: i32,
+}
+
+/// Both oracle prices read for a single instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceSnapshot {
+    pub zbtc_price: Price,
+    /// `price - conf`, truncated: values zBTC coming into the protocol
+    pub zbtc_bid: Price,
+    /// `price + conf`, rounded up: values zBTC paid out by the protocol
+    pub zbtc_ask: Price,
+    pub zbtc_ema: Price,
+    pub zbtc_conf: u64,
+    pub zbtc_expo: i32,
+    pub zbtc_publish_time: i64,
+    /// Median of the fresh sBTC publisher submissions
+    pub sbtc_price: Price,
+    /// Oldest timestamp among the submissions that made up the median
+    pub sbtc_last_update: i64,
+}
+
+impl PriceSnapshot {
+    pub fn load(
+        config: &Config,
+        zbtc_price_update: &AccountInfo,
+        sbtc_oracle_state: &AccountInfo,
+        clock: &Clock,
+    ) -> Result<Self> {
+        let zbtc = read_pyth_price(zbtc_price_update, &config.zbtc_pyth_feed_id, clock)?;
+        check_confidence(&zbtc, config.max_conf_bps)?;
+        let conf = i64::try_from(zbtc.conf).map_err(|_| ErrorCode::HighConfidence)?;
+
+        let zbtc_price = Price::from_pyth(zbtc.price, zbtc.expo)?;
+        let zbtc_ema = Price::from_pyth(zbtc.ema_price, zbtc.ema_expo)?;
+        check_ema_deviation(zbtc_price, zbtc_ema, config.max_ema_deviation_bps)?;
+
+        let submissions = read_sbtc_oracle(sbtc_oracle_state, &config.sbtc_oracle_program_id)?;
+        let (sbtc_price_cents, sbtc_last_update) = aggregate_sbtc_price(
+            &submissions,
+            clock.unix_timestamp,
+            config.sbtc_oracle_max_age,
+            config.sbtc_oracle_min_publishers,
+            config.sbtc_oracle_max_spread_bps,
+        )?;
+
+        Ok(Self {
+            zbtc_price,
+            zbtc_bid: Price::from_pyth(zbtc.price - conf, zbtc.expo)?,
+            zbtc_ask: Price::from_pyth_ceil(zbtc.price.checked_add(conf).ok_or(ErrorCode::InvalidPrice)?, zbtc.expo)?,
+            zbtc_ema,
+            zbtc_conf: zbtc.conf,
+            zbtc_expo: zbtc.expo,
+            zbtc_publish_time: zbtc.publish_time,
+            sbtc_price: Price::from_cents(sbtc_price_cents),
+            sbtc_last_update,
+        })
+    }
+}
+
+/// Reads a Pyth receiver `PriceUpdateV2` for `feed_id`, requiring full Wormhole verification
+/// and a publish time no older than `ORACLE_MAX_AGE`. With the `mock-oracle` feature, accounts
+/// owned by the mock-pyth program are accepted as a fallback for devnet/localnet.
+pub fn read_pyth_price(price_update: &AccountInfo, feed_id: &[u8; 32], clock: &Clock) -> Result<PythPrice> {
+    #[cfg(feature = "mock-oracle")]
+    if *price_update.owner == mock_pyth::ID {
+        return parse_mock_pyth(&price_update.try_borrow_data()?);
+    }
+
+    require_keys_eq!(*price_update.owner, pyth_solana_receiver_sdk::ID, ErrorCode::InvalidPythAccount);
+    let update = PriceUpdateV2::try_deserialize(&mut &price_update.try_borrow_data()?[..])
+        .map_err(|_| ErrorCode::InvalidPythAccount)?;
+
+    require!(update.verification_level == VerificationLevel::Full, ErrorCode::InsufficientVerificationLevel);
+    let price = update
+        .get_price_no_older_than(clock, ORACLE_MAX_AGE, feed_id)
+        .map_err(|err| match err {
+            GetPriceError::PriceTooOld => ErrorCode::StaleOraclePrice,
+            GetPriceError::MismatchedFeedId => ErrorCode::InvalidPythFeedId,
+            _ => ErrorCode::InsufficientVerificationLevel,
+        })?;
+
+    Ok(PythPrice {
+        price: price.price,
+        conf: price.conf,
+        expo: price.exponent,
+        publish_time: price.publish_time,
+        // The price message carries the EMA for the same feed and exponent
+        ema_price: update.price_message.ema_price,
+        ema_expo: price.exponent,
+    })
+}
+
+/// Parses a mock-pyth `PriceAccount`, checking its discriminator.
+#[cfg(feature = "mock-oracle")]
+pub fn parse_mock_pyth(data: &[u8]) -> Result<PythPrice> {
+    let account = mock_pyth::PriceAccount::try_deserialize(&mut &data[..])
+        .map_err(|_| ErrorCode::InvalidPythAccount)?;
+
+    Ok(PythPrice {
+        price: account.price,
+        conf: account.conf,
+        expo: account.expo,
+        publish_time: account.publish_time,
+        ema_price: account.ema_price,
+        ema_expo: account.ema_expo,
+    })
+}
+
+/// Reads the publisher submissions from an `OracleState` account owned by `program_id`.
+pub fn read_sbtc_oracle(oracle_state: &AccountInfo, program_id: &Pubkey) -> Result<Vec<Submission>> {
+    require_keys_eq!(*oracle_state.owner, *program_id, ErrorCode::InvalidOracleAccount);
+    parse_sbtc_oracle(&oracle_state.try_borrow_data()?)
+}
+
+/// Deserializes an `OracleState` after checking its length and discriminator.
+pub fn parse_sbtc_oracle(data: &[u8]) -> Result<Vec<Submission>> {
+    require!(
+        data.len() >= OracleState::DISCRIMINATOR.len() + OracleState::INIT_SPACE,
+        ErrorCode::InvalidOracleData
+    );
+    require!(data.starts_with(OracleState::DISCRIMINATOR), ErrorCode::InvalidOracleData);
+
+    let state = OracleState::try_deserialize(&mut &data[..])
+        .map_err(|_| ErrorCode::InvalidOracleData)?;
+
+    Ok(state.submissions)
+}
+
+/// Aggregates sBTC submissions into `(median_price, oldest_timestamp)`. Only submissions passing
+/// `check_sbtc_freshness` count; at least `min_publishers` of them are required and their
+/// max-min spread must stay within `max_spread_bps` of the median.
+pub fn aggregate_sbtc_price(
+    submissions: &[Submission],
+    now: i64,
+    max_age: u64,
+    min_publishers: u8,
+    max_spread_bps: u64,
+) -> Result<(u64, i64)> {
+    let fresh: Vec<&Submission> = submissions
+        .iter()
+        .filter(|s| s.price > 0 && check_sbtc_freshness(s.timestamp, now, max_age).is_ok())
+        .collect();
+    require!(
+        !fresh.is_empty() && fresh.len() >= min_publishers as usize,
+        ErrorCode::InsufficientOracleQuorum
+    );
+
+    let mut prices: Vec<u64> = fresh.iter().map(|s| s.price).collect();
+    prices.sort_unstable();
+    let mid = prices.len() / 2;
+    let median = if prices.len().is_multiple_of(2) {
+        ((prices[mid - 1] as u128 + prices[mid] as u128) / 2) as u64
+    } else {
+        prices[mid]
+    };
+
+    let spread_bps = (prices[prices.len() - 1] - prices[0]) as u128 * 10_000 / median as u128;
+    require!(spread_bps <= max_spread_bps as u128, ErrorCode::OracleSpreadTooWide);
+
+    let oldest = fresh.iter().map(|s| s.timestamp).min().unwrap_or(now);
+    Ok((median, oldest))
+}
+
+/// Rejects an sBTC oracle update that is older than `max_age` seconds or in the future.
+pub fn check_sbtc_freshness(last_update: i64, now: i64, max_age: u64) -> Result<()> {
+    require!(last_update <= now, ErrorCode::InvalidOracleTimestamp);
+    require!(now.abs_diff(last_update) <= max_age, ErrorCode::StaleOraclePrice);
+    Ok(())
+}
+
+/// Circuit breaker on the sBTC price: rejects a move of more than `sbtc_max_price_change_bps`
+/// per `sbtc_price_change_window` seconds away from the last anchored price. Less than one
+/// window since the anchor still allows one window's worth of change. Passes when no price
+/// has been anchored yet.
+pub fn check_sbtc_price_change(config: &Config, price: u64, now: i64) -> Result<()> {
+    let last_price = config.last_sbtc_price_cents;
+    if last_price == 0 {
+        return Ok(());
+    }
+
+    let elapsed = now.saturating_sub(config.last_sbtc_price_at).max(0) as u64;
+    let windows = (elapsed / config.sbtc_price_change_window).max(1);
+    let allowed_bps = (config.sbtc_max_price_change_bps as u128).saturating_mul(windows as u128);
+    let change_bps = (price.abs_diff(last_price) as u128) * 10_000 / last_price as u128;
+    require!(change_bps <= allowed_bps, ErrorCode::SbtcPriceChangeTooFast);
+
+    Ok(())
+}
+
+/// Rejects a zBTC price whose confidence interval exceeds `max_conf_bps` of the price.
+pub fn check_confidence(price: &PythPrice, max_conf_bps: u64) -> Result<()> {
+    require!(price.price > 0, ErrorCode::InvalidPrice);
+    require!(
+        price.conf as u128 * 10_000 <= price.price as u128 * max_conf_bps as u128,
+        ErrorCode::HighConfidence
+    );
+    Ok(())
+}
+
+/// Rejects a spot zBTC price further than `max_deviation_bps` from its EMA.
+pub fn check_ema_deviation(spot: Price, ema: Price, max_deviation_bps: u64) -> Result<()> {
+    require!(ema.raw() > 0, ErrorCode::InvalidPrice);
+    let deviation_bps = spot.raw().abs_diff(ema.raw()).saturating_mul(10_000) / ema.raw();
+    require!(deviation_bps <= max_deviation_bps as u128, ErrorCode::EmaDeviationTooHigh);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SBTC_ORACLE_MAX_SPREAD_BPS, ZBTC_MAX_CONF_BPS, ZBTC_MAX_EMA_DEVIATION_BPS};
+    use pyth_solana_receiver_sdk::price_update::PriceFeedMessage;
+
+    const FEED_ID: [u8; 32] = [7u8; 32];
+    const RECEIVER_ID: Pubkey = pyth_solana_receiver_sdk::ID;
+
+    fn price_update_data(feed_id: [u8; 32], verification_level: VerificationLevel, publish_time: i64) -> Vec<u8> {
+        let account = PriceUpdateV2 {
+            write_authority: Pubkey::new_unique(),
+            verification_level,
+            price_message: PriceFeedMessage {
+                feed_id,
+                price: 10_000_000_000_000,
+                conf: 500,
+                exponent: -8,
+                publish_time,
+                prev_publish_time: publish_time - 1,
+                ema_price: 10_000_000_000_000,
+                ema_conf: 500,
+            },
+            posted_slot: 0,
+        };
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    #[cfg(feature = "mock-oracle")]
+    fn mock_pyth_data(price: i64, conf: u64, expo: i32, publish_time: i64) -> Vec<u8> {
+        mock_pyth_data_with_ema(price, conf, expo, publish_time, price)
+    }
+
+    #[cfg(feature = "mock-oracle")]
+    fn mock_pyth_data_with_ema(price: i64, conf: u64, expo: i32, publish_time: i64, ema_price: i64) -> Vec<u8> {
+        let account = mock_pyth::PriceAccount {
+            price,
+            conf,
+            expo,
+            publish_time,
+            ema_price,
+            ema_expo: expo,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    /// Same field layout as mock-pyth, but without its discriminator
+    fn raw_price_data(price: i64, conf: u64, expo: i32, publish_time: i64) -> Vec<u8> {
+        let mut data = vec![0u8; 8];
+        data.extend_from_slice(&price.to_le_bytes());
+        data.extend_from_slice(&conf.to_le_bytes());
+        data.extend_from_slice(&expo.to_le_bytes());
+        data.extend_from_slice(&publish_time.to_le_bytes());
+        data
+    }
+
+    fn submissions(entries: &[(u64, i64)]) -> Vec<Submission> {
+        entries
+            .iter()
+            .map(|&(price, timestamp)| Submission { publisher: Pubkey::new_unique(), price, timestamp })
+            .collect()
+    }
+
+    /// Serialized `OracleState`, padded to its allocated size like the on-chain account
+    fn sbtc_oracle_data(entries: &[(u64, i64)]) -> Vec<u8> {
+        let state = OracleState { authority: Pubkey::new_unique(), bump: 255, submissions: submissions(entries) };
+        let mut data = Vec::new();
+        state.try_serialize(&mut data).unwrap();
+        data.resize(OracleState::DISCRIMINATOR.len() + OracleState::INIT_SPACE, 0);
+        data
+    }
+
+    #[test]
+    fn confidence_check_uses_configured_bps() {
+        let price =
+            PythPrice { price: 10_000_000, conf: 10_000, expo: -8, publish_time: 0, ema_price: 10_000_000, ema_expo: -8 };
+        assert!(check_confidence(&price, 10).is_ok());
+        assert_eq!(check_confidence(&price, 9).unwrap_err(), ErrorCode::HighConfidence.into());
+        assert_eq!(
+            check_confidence(&PythPrice { price: 0, ..price }, 10).unwrap_err(),
+            ErrorCode::InvalidPrice.into()
+        );
+    }
+
+    #[test]
+    fn ema_deviation_uses_configured_band() {
+        let ema = Price::from_cents(10_000_000);
+        assert!(check_ema_deviation(Price::from_cents(10_200_000), ema, 200).is_ok());
+        assert!(check_ema_deviation(Price::from_cents(9_800_000), ema, 200).is_ok());
+        assert_eq!(
+            check_ema_deviation(Price::from_cents(10_201_000), ema, 200).unwrap_err(),
+            ErrorCode::EmaDeviationTooHigh.into()
+        );
+        assert_eq!(
+            check_ema_deviation(ema, Price::default(), 200).unwrap_err(),
+            ErrorCode::InvalidPrice.into()
+        );
+    }
+
+    #[cfg(feature = "mock-oracle")]
+    #[test]
+    fn parse_mock_pyth_reads_price_account() {
+        let data = mock_pyth_data(10_000_000_000_000, 500, -8, 1_700_000_000);
+        let price = parse_mock_pyth(&data).unwrap();
+
+        assert_eq!(
+            price,
+            PythPrice {
+                price: 10_000_000_000_000,
+                conf: 500,
+                expo: -8,
+                publish_time: 1_700_000_000,
+                ema_price: 10_000_000_000_000,
+                ema_expo: -8,
+            }
+        );
+    }
+
+    #[cfg(feature = "mock-oracle")]
+    #[test]
+    fn parse_mock_pyth_rejects_short_or_foreign_account() {
+        let data = mock_pyth_data(1, 1, -8, 1);
+        assert_eq!(parse_mock_pyth(&data[..20]).unwrap_err(), ErrorCode::InvalidPythAccount.into());
+        assert_eq!(
+            parse_mock_pyth(&raw_price_data(1, 1, -8, 1)).unwrap_err(),
+            ErrorCode::InvalidPythAccount.into()
+        );
+    }
+
+    #[test]
+    fn sbtc_freshness_allows_updates_within_max_age() {
+        assert!(check_sbtc_freshness(1_000, 1_000, 300).is_ok());
+        assert!(check_sbtc_freshness(700, 1_000, 300).is_ok());
+    }
+
+    fn breaker_config(last_sbtc_price_cents: u64, last_sbtc_price_at: i64) -> Config {
+        Config {
+            sbtc_max_price_change_bps: 500,
+            sbtc_price_change_window: 3_600,
+            last_sbtc_price_cents,
+            last_sbtc_price_at,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn price_change_passes_without_anchor_or_within_threshold() {
+        assert!(check_sbtc_price_change(&breaker_config(0, 0), 1, 1_000).is_ok());
+
+        let config = breaker_config(10_000_000, 1_000);
+        assert!(check_sbtc_price_change(&config, 10_500_000, 1_000).is_ok());
+        assert!(check_sbtc_price_change(&config, 9_500_000, 1_010).is_ok());
+    }
+
+    #[test]
+    fn price_change_rejects_jump_within_window() {
+        let config = breaker_config(10_000_000, 1_000);
+        assert_eq!(
+            check_sbtc_price_change(&config, 10_501_000, 1_000 + 3_599).unwrap_err(),
+            ErrorCode::SbtcPriceChangeTooFast.into()
+        );
+        assert_eq!(
+            check_sbtc_price_change(&config, 5_000_000, 1_000).unwrap_err(),
+            ErrorCode::SbtcPriceChangeTooFast.into()
+        );
+    }
+
+    #[test]
+    fn price_change_allowance_scales_with_elapsed_windows() {
+        let config = breaker_config(10_000_000, 1_000);
+        // 3 windows elapsed: up to 15%
+        assert!(check_sbtc_price_change(&config, 11_500_000, 1_000 + 3 * 3_600).is_ok());
+        assert_eq!(
+            check_sbtc_price_change(&config, 11_600_000, 1_000 + 3 * 3_600).unwrap_err(),
+            ErrorCode::SbtcPriceChangeTooFast.into()
+        );
+    }
+
+    #[test]
+    fn accepted_price_reanchors_only_after_a_full_window() {
+        let mut config = breaker_config(0, 0);
+        config.accept_sbtc_price(10_000_000, 1_000).unwrap();
+        assert_eq!((config.last_sbtc_price_cents, config.last_sbtc_price_at), (10_000_000, 1_000));
+
+        config.accept_sbtc_price(10_400_000, 2_000).unwrap();
+        assert_eq!((config.last_sbtc_price_cents, config.last_sbtc_price_at), (10_000_000, 1_000));
+        // Drift is measured from the anchor, not the previous accepted price
+        assert!(config.accept_sbtc_price(10_900_000, 3_000).is_err());
+
+        config.accept_sbtc_price(10_400_000, 4_600).unwrap();
+        assert_eq!((config.last_sbtc_price_cents, config.last_sbtc_price_at), (10_400_000, 4_600));
+    }
+
+    #[test]
+    fn sbtc_freshness_rejects_stale_and_future_updates() {
+        assert_eq!(check_sbtc_freshness(699, 1_000, 300).unwrap_err(), ErrorCode::StaleOraclePrice.into());
+        assert_eq!(check_sbtc_freshness(0, i64::MAX, 300).unwrap_err(), ErrorCode::StaleOraclePrice.into());
+        assert_eq!(check_sbtc_freshness(1_001, 1_000, 300).unwrap_err(), ErrorCode::InvalidOracleTimestamp.into());
+    }
+
+    #[test]
+    fn parse_sbtc_oracle_reads_submissions() {
+        let data = sbtc_oracle_data(&[(10_000_000, 1_700_000_000), (10_100_000, 1_700_000_005)]);
+        let parsed = parse_sbtc_oracle(&data).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!((parsed[0].price, parsed[0].timestamp), (10_000_000, 1_700_000_000));
+        assert_eq!((parsed[1].price, parsed[1].timestamp), (10_100_000, 1_700_000_005));
+    }
+
+    #[test]
+    fn parse_sbtc_oracle_rejects_short_or_foreign_account() {
+        assert_eq!(parse_sbtc_oracle(&[]).unwrap_err(), ErrorCode::InvalidOracleData.into());
+        assert_eq!(
+            parse_sbtc_oracle(&sbtc_oracle_data(&[(1, 1)])[..20]).unwrap_err(),
+            ErrorCode::InvalidOracleData.into()
+        );
+        assert_eq!(
+            parse_sbtc_oracle(&raw_price_data(1, 1, -8, 1)).unwrap_err(),
+            ErrorCode::InvalidOracleData.into()
+        );
+
+        let mut data = sbtc_oracle_data(&[(1, 1)]);
+        data[0] ^= 0xff;
+        assert_eq!(parse_sbtc_oracle(&data).unwrap_err(), ErrorCode::InvalidOracleData.into());
+    }
+
+    fn load_snapshot(pyth_owner: &Pubkey, pyth_data: Vec<u8>) -> Result<PriceSnapshot> {
+        load_snapshot_with_sbtc(pyth_owner, pyth_data, &sbtc_oracle::ID, &[(9_500_000, 1_700_000_010)])
+    }
+
+    fn load_snapshot_with_sbtc(
+        pyth_owner: &Pubkey,
+        mut pyth_data: Vec<u8>,
+        oracle_owner: &Pubkey,
+        sbtc_submissions: &[(u64, i64)],
+    ) -> Result<PriceSnapshot> {
+        let config = Config {
+            zbtc_pyth_feed_id: FEED_ID,
+            sbtc_oracle_max_age: ORACLE_MAX_AGE,
+            sbtc_oracle_program_id: sbtc_oracle::ID,
+            sbtc_oracle_min_publishers: 1,
+            sbtc_oracle_max_spread_bps: SBTC_ORACLE_MAX_SPREAD_BPS,
+            max_conf_bps: ZBTC_MAX_CONF_BPS,
+            max_ema_deviation_bps: ZBTC_MAX_EMA_DEVIATION_BPS,
+            ..Config::default()
+        };
+        let (pyth_key, oracle_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut pyth_lamports, mut oracle_lamports) = (0u64, 0u64);
+        let mut oracle_data = sbtc_oracle_data(sbtc_submissions);
+
+        let pyth_account = AccountInfo::new(&pyth_key, false, false, &mut pyth_lamports, &mut pyth_data, pyth_owner, false, 0);
+        let oracle_account = AccountInfo::new(&oracle_key, false, false, &mut oracle_lamports, &mut oracle_data, oracle_owner, false, 0);
+        let clock = Clock { unix_timestamp: 1_700_000_020, ..Clock::default() };
+
+        PriceSnapshot::load(&config, &pyth_account, &oracle_account, &clock)
+    }
+
+    fn expected_snapshot() -> PriceSnapshot {
+        PriceSnapshot {
+            zbtc_price: Price::from_cents(10_000_000),
+            zbtc_bid: Price::from_raw(99_999_999_995_000_000),
+            zbtc_ask: Price::from_raw(100_000_000_005_000_000),
+            zbtc_ema: Price::from_cents(10_000_000),
+            zbtc_conf: 500,
+            zbtc_expo: -8,
+            zbtc_publish_time: 1_700_000_000,
+            sbtc_price: Price::from_cents(9_500_000),
+            sbtc_last_update: 1_700_000_010,
+        }
+    }
+
+    #[test]
+    fn snapshot_loads_price_update_and_sbtc_oracle() {
+        let data = price_update_data(FEED_ID, VerificationLevel::Full, 1_700_000_000);
+        assert_eq!(load_snapshot(&RECEIVER_ID, data).unwrap(), expected_snapshot());
+    }
+
+    #[test]
+    fn snapshot_ignores_stale_and_future_sbtc_submissions() {
+        let data = price_update_data(FEED_ID, VerificationLevel::Full, 1_700_000_000);
+        assert_eq!(
+            load_snapshot_with_sbtc(&RECEIVER_ID, data.clone(), &sbtc_oracle::ID, &[(9_500_000, 1_700_000_020 - 301)])
+                .unwrap_err(),
+            ErrorCode::InsufficientOracleQuorum.into()
+        );
+        assert_eq!(
+            load_snapshot_with_sbtc(&RECEIVER_ID, data.clone(), &sbtc_oracle::ID, &[(9_500_000, 1_700_000_021)])
+                .unwrap_err(),
+            ErrorCode::InsufficientOracleQuorum.into()
+        );
+        assert_eq!(
+            load_snapshot_with_sbtc(
+                &RECEIVER_ID,
+                data,
+                &sbtc_oracle::ID,
+                &[(1, 1_700_000_020 - 301), (9_500_000, 1_700_000_010), (u64::MAX, 1_700_000_021)],
+            )
+            .unwrap(),
+            expected_snapshot()
+        );
+    }
+
+    #[test]
+    fn aggregate_sbtc_price_takes_median() {
+        let odd = submissions(&[(9_520_000, 995), (9_500_000, 990), (9_510_000, 1_000)]);
+        assert_eq!(aggregate_sbtc_price(&odd, 1_000, 300, 3, 200).unwrap(), (9_510_000, 990));
+
+        let even = submissions(&[(9_500_000, 1_000), (9_510_000, 1_000), (9_520_000, 1_000), (9_531_000, 1_000)]);
+        assert_eq!(aggregate_sbtc_price(&even, 1_000, 300, 3, 200).unwrap(), (9_515_000, 1_000));
+    }
+
+    #[test]
+    fn aggregate_sbtc_price_requires_quorum() {
+        let entries = submissions(&[(9_500_000, 1_000), (9_510_000, 1_000), (9_520_000, 500), (0, 0)]);
+        assert_eq!(aggregate_sbtc_price(&entries, 1_000, 300, 2, 200).unwrap(), (9_505_000, 1_000));
+        assert_eq!(
+            aggregate_sbtc_price(&entries, 1_000, 300, 3, 200).unwrap_err(),
+            ErrorCode::InsufficientOracleQuorum.into()
+        );
+        assert_eq!(aggregate_sbtc_price(&[], 1_000, 300, 0, 200).unwrap_err(), ErrorCode::InsufficientOracleQuorum.into());
+    }
+
+    #[test]
+    fn aggregate_sbtc_price_rejects_wide_spread() {
+        // One publisher 10% off: the median would hold, but the disagreement halts the feed
+        let entries = submissions(&[(9_500_000, 1_000), (9_510_000, 1_000), (10_450_000, 1_000)]);
+        assert_eq!(
+            aggregate_sbtc_price(&entries, 1_000, 300, 3, 200).unwrap_err(),
+            ErrorCode::OracleSpreadTooWide.into()
+        );
+        assert_eq!(aggregate_sbtc_price(&entries, 1_000, 300, 3, 1_000).unwrap(), (9_510_000, 1_000));
+    }
+
+    #[test]
+    fn read_sbtc_oracle_checks_owner_against_configured_program() {
+        let (key, program_id) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut lamports = 0u64;
+        let mut data = sbtc_oracle_data(&[(9_500_000, 1_700_000_010)]);
+        let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &program_id, false, 0);
+
+        assert_eq!(read_sbtc_oracle(&account, &program_id).unwrap()[0].price, 9_500_000);
+        assert_eq!(
+            read_sbtc_oracle(&account, &sbtc_oracle::ID).unwrap_err(),
+            ErrorCode::InvalidOracleAccount.into()
+        );
+    }
+
+    #[test]
+    fn snapshot_rejects_sbtc_oracle_not_owned_by_sbtc_oracle_program() {
+        let data = price_update_data(FEED_ID, VerificationLevel::Full, 1_700_000_000);
+        assert_eq!(
+            load_snapshot_with_sbtc(&RECEIVER_ID, data, &Pubkey::new_unique(), &[(9_500_000, 1_700_000_010)])
+                .unwrap_err(),
+            ErrorCode::InvalidOracleAccount.into()
+        );
+    }
+
+    #[test]
+    fn snapshot_rejects_price_update_for_other_feed() {
+        let data = price_update_data([9u8; 32], VerificationLevel::Full, 1_700_000_000);
+        assert_eq!(load_snapshot(&RECEIVER_ID, data).unwrap_err(), ErrorCode::InvalidPythFeedId.into());
+    }
+
+    #[test]
+    fn snapshot_rejects_partially_verified_price_update() {
+        let data = price_update_data(FEED_ID, VerificationLevel::Partial { num_signatures: 12 }, 1_700_000_000);
+        assert_eq!(
+            load_snapshot(&RECEIVER_ID, data).unwrap_err(),
+            ErrorCode::InsufficientVerificationLevel.into()
+        );
+    }
+
+    #[test]
+    fn snapshot_rejects_stale_price_update() {
+        let data = price_update_data(FEED_ID, VerificationLevel::Full, 1_700_000_020 - ORACLE_MAX_AGE as i64 - 1);
+        assert_eq!(load_snapshot(&RECEIVER_ID, data).unwrap_err(), ErrorCode::StaleOraclePrice.into());
+    }
+
+    #[test]
+    fn snapshot_rejects_price_update_not_owned_by_receiver() {
+        let data = price_update_data(FEED_ID, VerificationLevel::Full, 1_700_000_000);
+        assert_eq!(
+            load_snapshot(&Pubkey::new_unique(), data).unwrap_err(),
+            ErrorCode::InvalidPythAccount.into()
+        );
+        assert_eq!(
+            load_snapshot(&RECEIVER_ID, raw_price_data(10_000_000_000_000, 500, -8, 1_700_000_000)).unwrap_err(),
+            ErrorCode::InvalidPythAccount.into()
+        );
+    }
+
+    #[cfg(feature = "mock-oracle")]
+    #[test]
+    fn snapshot_loads_mock_pyth_and_sbtc_oracle() {
+        let data = mock_pyth_data(10_000_000_000_000, 500, -8, 1_700_000_000);
+        assert_eq!(load_snapshot(&mock_pyth::ID, data).unwrap(), expected_snapshot());
+    }
+
+    #[cfg(feature = "mock-oracle")]
+    #[test]
+    fn snapshot_applies_confidence_limit_to_mock_pyth() {
+        let data = mock_pyth_data(10_000_000_000_000, 10_000_000_001, -8, 1_700_000_000);
+        assert_eq!(load_snapshot(&mock_pyth::ID, data).unwrap_err(), ErrorCode::HighConfidence.into());
+    }
+
+    #[cfg(feature = "mock-oracle")]
+    #[test]
+    fn snapshot_applies_ema_band_to_mock_pyth() {
+        let data = mock_pyth_data_with_ema(10_000_000_000_000, 500, -8, 1_700_000_000, 9_000_000_000_000);
+        assert_eq!(load_snapshot(&mock_pyth::ID, data).unwrap_err(), ErrorCode::EmaDeviationTooHigh.into());
+    }
+
+    #[cfg(feature = "mock-oracle")]
+    #[test]
+    fn snapshot_rejects_mock_layout_not_owned_by_mock_pyth() {
+        let data = mock_pyth_data(10_000_000_000_000, 500, -8, 1_700_000_000);
+        assert_eq!(
+            load_snapshot(&Pubkey::new_unique(), data).unwrap_err(),
+            ErrorCode::InvalidPythAccount.into()
+        );
+    }
+}
@@ -0,0 +1,231 @@
+// Fixed-point USD prices used by the swap and collateral math.
+
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+
+/// Decimals of a `Price`, i.e. 1e-12 USD precision
+pub const PRICE_DECIMALS: u32 = 12;
+
+const PRICE_SCALE: u128 = 10u128.pow(PRICE_DECIMALS);
+const CENTS_SCALE: u128 = PRICE_SCALE / 100;
+
+/// USD per whole token, scaled by `10^PRICE_DECIMALS`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Price(u128);
+
+impl Price {
+    pub const fn from_raw(raw: u128) -> Self {
+        Self(raw)
+    }
+
+    pub const fn raw(self) -> u128 {
+        self.0
+    }
+
+    /// Pyth `price * 10^expo`, truncated to `PRICE_DECIMALS`.
+    pub fn from_pyth(price: i64, expo: i32) -> Result<Self> {
+        Self::from_scaled(price, expo, false)
+    }
+
+    /// Pyth `price * 10^expo`, rounded up to `PRICE_DECIMALS`.
+    pub fn from_pyth_ceil(price: i64, expo: i32) -> Result<Self> {
+        Self::from_scaled(price, expo, true)
+    }
+
+    pub fn from_cents(cents: u64) -> Self {
+        Self(cents as u128 * CENTS_SCALE)
+    }
+
+    /// Truncated to whole cents, saturating at `u64::MAX`.
+    pub fn to_cents(self) -> u64 {
+        u64::try_from(self.0 / CENTS_SCALE).unwrap_or(u64::MAX)
+    }
+
+    /// USD value, scaled by `10^PRICE_DECIMALS`, of `amount` minor units of a token with
+    /// `decimals`. Truncates.
+    pub fn value_of(self, amount: u128, decimals: u8) -> Result<u128> {
+        let value = amount
+            .checked_mul(self.0)
+            .ok_or(ErrorCode::InvalidAmount)?
+            .checked_div(pow10(decimals as u32)?)
+            .ok_or(ErrorCode::InvalidAmount)?;
+        Ok(value)
+    }
+
+    /// Minor units of a token with `decimals` worth `value` (scaled by `10^PRICE_DECIMALS`).
+    /// Truncates.
+    pub fn amount_for(self, value: u128, decimals: u8) -> Result<u128> {
+        require!(self.0 > 0, ErrorCode::InvalidPrice);
+        let amount = value
+            .checked_mul(pow10(decimals as u32)?)
+            .ok_or(ErrorCode::InvalidAmount)?
+            / self.0;
+        Ok(amount)
+    }
+
+    /// `value_of`, rounded up.
+    pub fn value_of_ceil(self, amount: u128, decimals: u8) -> Result<u128> {
+        let value = amount
+            .checked_mul(self.0)
+            .ok_or(ErrorCode::InvalidAmount)?
+            .div_ceil(pow10(decimals as u32)?);
+        Ok(value)
+    }
+
+    /// `amount_for`, rounded up.
+    pub fn amount_for_ceil(self, value: u128, decimals: u8) -> Result<u128> {
+        require!(self.0 > 0, ErrorCode::InvalidPrice);
+        let amount = value
+            .checked_mul(pow10(decimals as u32)?)
+            .ok_or(ErrorCode::InvalidAmount)?
+            .div_ceil(self.0);
+        Ok(amount)
+    }
+
+    fn from_scaled(price: i64, expo: i32, round_up: bool) -> Result<Self> {
+        require!(price > 0, ErrorCode::InvalidPrice);
+        let price = price as u128;
+
+        // expo + PRICE_DECIMALS cannot overflow in i64
+        let shift = expo as i64 + PRICE_DECIMALS as i64;
+        let raw = if shift >= 0 {
+            let scale = u32::try_from(shift).map_err(|_| ErrorCode::InvalidPrice)?;
+            10u128
+                .checked_pow(scale)
+                .and_then(|scale| price.checked_mul(scale))
+                .ok_or(ErrorCode::InvalidPrice)?
+        } else {
+            // A divisor beyond u128 leaves nothing above 1e-12 USD
+            let divisor = u32::try_from(-shift).ok().and_then(|s| 10u128.checked_pow(s));
+            match divisor {
+                Some(divisor) if round_up => price.div_ceil(divisor),
+                Some(divisor) => price / divisor,
+                None if round_up => 1,
+                None => 0,
+            }
+        };
+
+        require!(raw > 0, ErrorCode::InvalidPrice);
+        Ok(Self(raw))
+    }
+}
+
+/// Converts `amount` minor units of a token priced at `from_price` into minor units of a token
+/// priced at `to_price`, truncating at each step so the result never exceeds the exact value.
+pub fn convert(amount: u128, from_price: Price, from_decimals: u8, to_price: Price, to_decimals: u8) -> Result<u128> {
+    to_price.amount_for(from_price.value_of(amount, from_decimals)?, to_decimals)
+}
+
+/// Minor units of a token priced at `from_price` needed to receive at least `amount` minor units
+/// of a token priced at `to_price` through `convert`. Rounds up at each step.
+pub fn convert_ceil(amount: u128, to_price: Price, to_decimals: u8, from_price: Price, from_decimals: u8) -> Result<u128> {
+    from_price.amount_for_ceil(to_price.value_of_ceil(amount, to_decimals)?, from_decimals)
+}
+
+fn pow10(exp: u32) -> Result<u128> {
+    let pow = 10u128.checked_pow(exp).ok_or(ErrorCode::InvalidAmount)?;
+    Ok(pow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn from_pyth_scales_by_exponent() {
+        assert_eq!(Price::from_pyth(10_000_000_000_000, -8).unwrap(), Price::from_cents(10_000_000));
+        assert_eq!(Price::from_pyth(123_456, -3).unwrap().raw(), 123_456_000_000_000);
+        assert_eq!(Price::from_pyth(1_000, 0).unwrap(), Price::from_cents(100_000));
+        assert_eq!(Price::from_pyth(1, -12).unwrap().raw(), 1);
+    }
+
+    #[test]
+    fn from_pyth_rounds_below_precision() {
+        assert_eq!(Price::from_pyth(15, -13).unwrap().raw(), 1);
+        assert_eq!(Price::from_pyth_ceil(15, -13).unwrap().raw(), 2);
+        assert_eq!(Price::from_pyth_ceil(10, -13).unwrap().raw(), 1);
+        assert_eq!(Price::from_pyth(1, -13).unwrap_err(), ErrorCode::InvalidPrice.into());
+    }
+
+    #[test]
+    fn from_pyth_rejects_non_positive_and_overflow_without_panicking() {
+        assert!(Price::from_pyth(0, -8).is_err());
+        assert!(Price::from_pyth(-1, -8).is_err());
+        assert!(Price::from_pyth(1, 30).is_err());
+        assert!(Price::from_pyth(i64::MAX, i32::MAX).is_err());
+        assert!(Price::from_pyth(i64::MAX, i32::MIN).is_err());
+        assert_eq!(Price::from_pyth_ceil(i64::MAX, i32::MIN).unwrap().raw(), 1);
+    }
+
+    #[test]
+    fn cents_round_trip() {
+        assert_eq!(Price::from_cents(9_500_000).to_cents(), 9_500_000);
+        assert_eq!(Price::from_raw(CENTS_SCALE * 3 - 1).to_cents(), 2);
+    }
+
+    #[test]
+    fn convert_keeps_sub_cent_precision() {
+        // 1 sat at $100,000.005 (a price cents cannot represent) into an 8-decimal token at $1
+        let zbtc = Price::from_pyth(10_000_000_500_000, -8).unwrap();
+        let usd = Price::from_cents(100);
+        assert_eq!(convert(100_000_000, zbtc, 8, usd, 8).unwrap(), 10_000_000_500_000);
+    }
+
+    #[test]
+    fn convert_ceil_rounds_up() {
+        // 1 zBTC at $3 costs 2 units of a $2 token with 0 decimals, which convert back to 1.33 zBTC
+        let (zbtc, usd2) = (Price::from_cents(300), Price::from_cents(200));
+        assert_eq!(convert_ceil(100_000_000, zbtc, 8, usd2, 0).unwrap(), 2);
+        assert_eq!(convert(2, usd2, 0, zbtc, 8).unwrap(), 133_333_333);
+    }
+
+    #[test]
+    fn amount_for_rejects_zero_price() {
+        assert_eq!(Price::default().amount_for(1, 8).unwrap_err(), ErrorCode::InvalidPrice.into());
+    }
+
+    // The mint-then-burn round trip is covered in math.rs, on top of the fee split
+    proptest! {
+        #[test]
+        fn convert_never_exceeds_exact_value(
+            amount in 0u64..=u64::MAX,
+            from_raw in 1u128..=PRICE_SCALE * 1_000_000,
+            to_raw in 1u128..=PRICE_SCALE * 1_000_000,
+            decimals in 0u8..=9,
+        ) {
+            let (from, to) = (Price::from_raw(from_raw), Price::from_raw(to_raw));
+            let out = convert(amount as u128, from, decimals, to, decimals).unwrap();
+
+            // out * to <= amount * from, compared without overflow as from/to <= 1e18
+            prop_assert!(out * to_raw <= amount as u128 * from_raw);
+        }
+
+        #[test]
+        fn convert_ceil_inputs_always_reach_the_output(
+            amount in 1u64..=u64::MAX,
+            from_raw in 1u128..=PRICE_SCALE * 1_000_000,
+            to_raw in 1u128..=PRICE_SCALE * 1_000_000,
+            from_decimals in 0u8..=9,
+            to_decimals in 0u8..=9,
+        ) {
+            let (from, to) = (Price::from_raw(from_raw), Price::from_raw(to_raw));
+            let Ok(input) = convert_ceil(amount as u128, to, to_decimals, from, from_decimals) else {
+                return Ok(());
+            };
+            if let Ok(out) = convert(input, from, from_decimals, to, to_decimals) {
+                prop_assert!(out >= amount as u128);
+            }
+        }
+
+        #[test]
+        fn pyth_rounding_brackets_the_exact_price(price in 1i64..=i64::MAX, expo in -20i32..=4) {
+            let (floor, ceil) = (Price::from_pyth(price, expo), Price::from_pyth_ceil(price, expo));
+            if let (Ok(floor), Ok(ceil)) = (floor, ceil) {
+                prop_assert!(floor <= ceil);
+                prop_assert!(ceil.raw() - floor.raw() <= 1);
+            }
+        }
+    }
+}
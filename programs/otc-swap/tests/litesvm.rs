@@ -0,0 +1,992 @@
+// In-process tests running the compiled otc-swap, sbtc-oracle and mock-pyth programs in LiteSVM.
+//
+// Built only with the `svm-tests` feature, as they need the programs in `target/deploy`: run them
+// with `yarn test:svm`, which builds first, as CI does. A missing `.so` fails the test. zBTC/USD
+// prices are written directly as Pyth receiver `PriceUpdateV2` accounts; mock-pyth is loaded to
+// check that `anchor build` output, which lacks `mock-oracle`, rejects its price accounts.
+
+// LiteSVM's own `TransactionResult` carries the full failure metadata
+#![allow(clippy::result_large_err)]
+
+use std::path::PathBuf;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::{
+    system_program, AccountDeserialize, AccountSerialize, AnchorDeserialize, AnchorSerialize, Discriminator,
+    InstructionData, ToAccountMetas,
+};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use litesvm::types::TransactionResult;
+use litesvm::LiteSVM;
+use otc_swap::{BurnEvent, Config, DepositorRecord, ErrorCode, Quote, SystemHealth};
+use sbtc_oracle::{LegacyOracleState, OracleState, SMA_PERIOD};
+use pyth_solana_receiver_sdk::price_update::{PriceFeedMessage, PriceUpdateV2, VerificationLevel};
+use solana_sdk::account::Account;
+use solana_sdk::account_utils::StateMut;
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::clock::Clock;
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+const NOW: i64 = 1_750_000_000;
+const FEED_ID: [u8; 32] = [7u8; 32];
+const DECIMALS: u8 = 8;
+const ONE_BTC: u64 = 100_000_000;
+/// zBTC/USD at $100,000 with a $10 confidence interval (1 bps)
+const ZBTC_PRICE: i64 = 10_000_000_000_000;
+const ZBTC_CONF: u64 = 1_000_000_000;
+/// sBTC/USD at $95,000
+const SBTC_PRICE_CENTS: u64 = 9_500_000;
+const FEE_RATE_BPS: u64 = 30;
+const MIN_COLLATERAL_BPS: u64 = 20_000;
+
+struct Harness {
+    svm: LiteSVM,
+    multisig: Keypair,
+    user: Keypair,
+    /// One per `SBTC_ORACLE_MIN_PUBLISHERS`, all submitting the same price
+    publishers: Vec<Keypair>,
+    zbtc_mint: Pubkey,
+    sbtc_mint: Pubkey,
+    treasury_vault: Pubkey,
+    fee_vault: Pubkey,
+    user_zbtc: Pubkey,
+    user_sbtc: Pubkey,
+    price_update: Pubkey,
+    oracle_state: Pubkey,
+    config: Pubkey,
+}
+
+fn program_so(name: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../target/deploy").join(format!("{name}.so"));
+    assert!(path.exists(), "{} not found, run `anchor build` first", path.display());
+    path
+}
+
+/// Deploys `path` behind the upgradeable loader with `upgrade_authority`, as `anchor deploy` would.
+fn add_upgradeable_program(svm: &mut LiteSVM, program_id: Pubkey, path: PathBuf, upgrade_authority: Pubkey) {
+    let elf = std::fs::read(path).unwrap();
+    let programdata_address = program_data_address(&program_id);
+
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+    let mut programdata = Account::new(0, metadata_len + elf.len(), &bpf_loader_upgradeable::ID);
+    programdata
+        .set_state(&UpgradeableLoaderState::ProgramData { slot: 0, upgrade_authority_address: Some(upgrade_authority) })
+        .unwrap();
+    programdata.data[metadata_len..].copy_from_slice(&elf);
+    programdata.lamports = svm.minimum_balance_for_rent_exemption(programdata.data.len());
+    // The program data must exist before the program account referencing it is loaded
+    svm.set_account(programdata_address, programdata).unwrap();
+
+    let mut program = Account::new(0, UpgradeableLoaderState::size_of_program(), &bpf_loader_upgradeable::ID);
+    program.set_state(&UpgradeableLoaderState::Program { programdata_address }).unwrap();
+    program.lamports = svm.minimum_balance_for_rent_exemption(program.data.len());
+    program.executable = true;
+    svm.set_account(program_id, program).unwrap();
+}
+
+fn program_data_address(program_id: &Pubkey) -> Pubkey {
+    pda(&[program_id.as_ref()], &bpf_loader_upgradeable::ID)
+}
+
+fn pda(seeds: &[&[u8]], program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(seeds, program_id).0
+}
+
+/// The `BurnEvent` a successful burn emitted.
+fn burn_event(result: TransactionResult) -> BurnEvent {
+    result
+        .unwrap()
+        .logs
+        .iter()
+        .filter_map(|log| log.strip_prefix("Program data: "))
+        .filter_map(|data| STANDARD.decode(data).ok())
+        .find_map(|data| {
+            let mut event = data.strip_prefix(BurnEvent::DISCRIMINATOR)?;
+            Some(BurnEvent::deserialize(&mut event).unwrap())
+        })
+        .expect("no BurnEvent logged")
+}
+
+fn custom_error(code: ErrorCode) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(code.into()))
+}
+
+impl Harness {
+    /// Loads both programs, funds the treasury with `treasury_zbtc` collateral and the user with
+    /// 10 zBTC, publishes fresh prices and runs `initialize`.
+    fn new(treasury_zbtc: u64) -> Self {
+        let otc_swap_so = program_so("otc_swap");
+        let sbtc_oracle_so = program_so("sbtc_oracle");
+
+        let (multisig, user) = (Keypair::new(), Keypair::new());
+        let publishers: Vec<Keypair> = (0..3).map(|_| Keypair::new()).collect();
+        let mut svm = LiteSVM::new();
+        svm.add_program_from_file(otc_swap::ID, otc_swap_so).unwrap();
+        svm.add_program_from_file(mock_pyth::ID, program_so("mock_pyth")).unwrap();
+        // sbtc-oracle's initialize is gated on its upgrade authority
+        add_upgradeable_program(&mut svm, sbtc_oracle::ID, sbtc_oracle_so, multisig.pubkey());
+
+        for signer in [&multisig, &user].into_iter().chain(&publishers) {
+            svm.airdrop(&signer.pubkey(), 10_000_000_000).unwrap();
+        }
+
+        let ms = multisig.pubkey();
+        let mut harness = Self {
+            svm,
+            zbtc_mint: Pubkey::new_unique(),
+            sbtc_mint: Pubkey::new_unique(),
+            treasury_vault: Pubkey::new_unique(),
+            fee_vault: Pubkey::new_unique(),
+            user_zbtc: Pubkey::new_unique(),
+            user_sbtc: Pubkey::new_unique(),
+            price_update: Pubkey::new_unique(),
+            oracle_state: pda(&[b"oracle"], &sbtc_oracle::ID),
+            config: pda(&[b"config_v1", ms.as_ref()], &otc_swap::ID),
+            multisig,
+            user,
+            publishers,
+        };
+        harness.warp_to(NOW);
+
+        let (zbtc_mint, sbtc_mint) = (harness.zbtc_mint, harness.sbtc_mint);
+        harness.set_mint(zbtc_mint, ms);
+        harness.set_mint(sbtc_mint, ms);
+        let treasury_auth = pda(&[b"treasury_auth_v1", ms.as_ref()], &otc_swap::ID);
+        let fee_auth = pda(&[b"fee_auth_v1", ms.as_ref()], &otc_swap::ID);
+        let user = harness.user.pubkey();
+        harness.set_token_account(harness.treasury_vault, zbtc_mint, treasury_auth, treasury_zbtc);
+        harness.set_token_account(harness.fee_vault, zbtc_mint, fee_auth, 0);
+        harness.set_token_account(harness.user_zbtc, zbtc_mint, user, 10 * ONE_BTC);
+        harness.set_token_account(harness.user_sbtc, sbtc_mint, user, 0);
+
+        harness.init_sbtc_oracle();
+        harness.submit_sbtc_price(SBTC_PRICE_CENTS).unwrap();
+        harness.set_zbtc_price(ZBTC_PRICE, NOW);
+        harness.initialize().unwrap();
+        harness
+    }
+
+    fn send(&mut self, ix: Instruction, signers: &[&Keypair]) -> TransactionResult {
+        let payer = signers[0].pubkey();
+        let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer), signers, self.svm.latest_blockhash());
+        let result = self.svm.send_transaction(tx);
+        // Identical instructions resent later must not be deduplicated
+        self.svm.expire_blockhash();
+        result
+    }
+
+    fn warp_to(&mut self, unix_timestamp: i64) {
+        let mut clock = self.svm.get_sysvar::<Clock>();
+        clock.unix_timestamp = unix_timestamp;
+        self.svm.set_sysvar(&clock);
+    }
+
+    fn set_mint(&mut self, mint: Pubkey, authority: Pubkey) {
+        let state = spl_token::state::Mint {
+            mint_authority: Some(authority).into(),
+            supply: 0,
+            decimals: DECIMALS,
+            is_initialized: true,
+            freeze_authority: Some(authority).into(),
+        };
+        let mut data = vec![0u8; spl_token::state::Mint::LEN];
+        state.pack_into_slice(&mut data);
+        self.set_account(mint, spl_token::ID, data);
+    }
+
+    fn set_token_account(&mut self, address: Pubkey, mint: Pubkey, owner: Pubkey, amount: u64) {
+        let state = spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        };
+        let mut data = vec![0u8; spl_token::state::Account::LEN];
+        state.pack_into_slice(&mut data);
+        self.set_account(address, spl_token::ID, data);
+    }
+
+    /// Posts a fully verified zBTC/USD update with the EMA equal to the spot price.
+    fn set_zbtc_price(&mut self, price: i64, publish_time: i64) {
+        let update = PriceUpdateV2 {
+            write_authority: Pubkey::new_unique(),
+            verification_level: VerificationLevel::Full,
+            price_message: PriceFeedMessage {
+                feed_id: FEED_ID,
+                price,
+                conf: ZBTC_CONF,
+                exponent: -8,
+                publish_time,
+                prev_publish_time: publish_time - 1,
+                ema_price: price,
+                ema_conf: ZBTC_CONF,
+            },
+            posted_slot: 0,
+        };
+        let mut data = Vec::new();
+        update.try_serialize(&mut data).unwrap();
+        self.set_account(self.price_update, pyth_solana_receiver_sdk::ID, data);
+    }
+
+    fn set_account(&mut self, address: Pubkey, owner: Pubkey, data: Vec<u8>) {
+        let account = Account {
+            lamports: self.svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.svm.set_account(address, account).unwrap();
+    }
+
+    fn init_sbtc_oracle(&mut self) {
+        let multisig = self.multisig.insecure_clone();
+        self.send_init_sbtc_oracle(&multisig).unwrap();
+        for publisher in self.publishers.iter().map(Keypair::pubkey).collect::<Vec<_>>() {
+            self.add_publisher(publisher);
+        }
+    }
+
+    fn add_publisher(&mut self, publisher: Pubkey) {
+        let accounts = sbtc_oracle::accounts::UpdatePublishers {
+            oracle_state: self.oracle_state,
+            authority: self.multisig.pubkey(),
+        };
+        let data = sbtc_oracle::instruction::AddPublisher { publisher }.data();
+        let ix = Instruction::new_with_bytes(sbtc_oracle::ID, &data, accounts.to_account_metas(None));
+        let multisig = self.multisig.insecure_clone();
+        self.send(ix, &[&multisig]).unwrap();
+    }
+
+    fn send_init_sbtc_oracle(&mut self, authority: &Keypair) -> TransactionResult {
+        let accounts = sbtc_oracle::accounts::Initialize {
+            oracle_state: self.oracle_state,
+            authority: authority.pubkey(),
+            program: sbtc_oracle::ID,
+            program_data: program_data_address(&sbtc_oracle::ID),
+            system_program: system_program::ID,
+        };
+        let ix = Instruction::new_with_bytes(
+            sbtc_oracle::ID,
+            &sbtc_oracle::instruction::Initialize {}.data(),
+            accounts.to_account_metas(None),
+        );
+        self.send(ix, &[authority])
+    }
+
+    /// Submits `price` from every publisher.
+    fn submit_sbtc_price(&mut self, price: u64) -> TransactionResult {
+        for publisher in self.publishers.iter().map(Keypair::insecure_clone).collect::<Vec<_>>() {
+            let accounts = sbtc_oracle::accounts::SubmitPrice {
+                oracle_state: self.oracle_state,
+                publisher: publisher.pubkey(),
+            };
+            let data = sbtc_oracle::instruction::SubmitPrice { price }.data();
+            let ix = Instruction::new_with_bytes(sbtc_oracle::ID, &data, accounts.to_account_metas(None));
+            self.send(ix, &[&publisher])?;
+        }
+        Ok(Default::default())
+    }
+
+    fn initialize(&mut self) -> TransactionResult {
+        let ms = self.multisig.pubkey();
+        let accounts = otc_swap::accounts::Initialize {
+            squad_multisig: ms,
+            sbtc_mint: self.sbtc_mint,
+            zbtc_mint: self.zbtc_mint,
+            sbtc_mint_authority_pda: pda(&[b"sbtc_mint_authority", ms.as_ref()], &otc_swap::ID),
+            treasury_authority_pda: pda(&[b"treasury_auth_v1", ms.as_ref()], &otc_swap::ID),
+            fee_authority_pda: pda(&[b"fee_auth_v1", ms.as_ref()], &otc_swap::ID),
+            treasury_zbtc_vault: self.treasury_vault,
+            fee_vault: self.fee_vault,
+            config: self.config,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+        };
+        let data = otc_swap::instruction::Initialize {
+            fee_rate_bps: FEE_RATE_BPS,
+            min_collateral_bps: MIN_COLLATERAL_BPS,
+            zbtc_pyth_feed_id: FEED_ID,
+            authorized_sbtc_oracle_state_pda: self.oracle_state,
+        }
+        .data();
+        let ix = Instruction::new_with_bytes(otc_swap::ID, &data, accounts.to_account_metas(None));
+        let multisig = self.multisig.insecure_clone();
+        self.send(ix, &[&multisig])
+    }
+
+    fn mint_sbtc(&mut self, zbtc_amount: u64) -> TransactionResult {
+        self.mint_sbtc_with_limits(zbtc_amount, 0, None)
+    }
+
+    fn mint_sbtc_with_limits(&mut self, zbtc_amount: u64, min_sbtc_out: u64, deadline: Option<i64>) -> TransactionResult {
+        self.send_mint(otc_swap::instruction::MintSbtc { zbtc_amount, min_sbtc_out, deadline }.data())
+    }
+
+    fn mint_sbtc_exact_out(&mut self, sbtc_out: u64, max_zbtc_in: u64) -> TransactionResult {
+        self.send_mint(otc_swap::instruction::MintSbtcExactOut { sbtc_out, max_zbtc_in, deadline: None }.data())
+    }
+
+    /// Sends `data` with the `MintSbtc` accounts, signed by the user.
+    fn send_mint(&mut self, data: Vec<u8>) -> TransactionResult {
+        let ms = self.multisig.pubkey();
+        let accounts = otc_swap::accounts::MintSbtc {
+            user: self.user.pubkey(),
+            squad_multisig: ms,
+            config: self.config,
+            zbtc_mint: self.zbtc_mint,
+            sbtc_mint: self.sbtc_mint,
+            user_zbtc_account: self.user_zbtc,
+            user_sbtc_account: self.user_sbtc,
+            treasury_zbtc_vault: self.treasury_vault,
+            fee_vault: self.fee_vault,
+            sbtc_mint_authority_pda: pda(&[b"sbtc_mint_authority", ms.as_ref()], &otc_swap::ID),
+            treasury_authority_pda: pda(&[b"treasury_auth_v1", ms.as_ref()], &otc_swap::ID),
+            fee_authority_pda: pda(&[b"fee_auth_v1", ms.as_ref()], &otc_swap::ID),
+            zbtc_price_update: self.price_update,
+            authorized_sbtc_oracle_state_pda: self.oracle_state,
+            token_program: spl_token::ID,
+        };
+        let ix = Instruction::new_with_bytes(otc_swap::ID, &data, accounts.to_account_metas(None));
+        let user = self.user.insecure_clone();
+        self.send(ix, &[&user])
+    }
+
+    fn burn_sbtc(&mut self, sbtc_amount: u64) -> TransactionResult {
+        self.burn_sbtc_with_limits(sbtc_amount, 0, None)
+    }
+
+    fn burn_sbtc_with_limits(&mut self, sbtc_amount: u64, min_zbtc_out: u64, deadline: Option<i64>) -> TransactionResult {
+        self.send_burn(otc_swap::instruction::BurnSbtc { sbtc_amount, min_zbtc_out, deadline }.data())
+    }
+
+    fn burn_sbtc_exact_out(&mut self, zbtc_out: u64, max_sbtc_in: u64) -> TransactionResult {
+        self.send_burn(otc_swap::instruction::BurnSbtcExactOut { zbtc_out, max_sbtc_in, deadline: None }.data())
+    }
+
+    /// Sends `data` with the `BurnSbtc` accounts, signed by the user.
+    fn send_burn(&mut self, data: Vec<u8>) -> TransactionResult {
+        let ms = self.multisig.pubkey();
+        let accounts = otc_swap::accounts::BurnSbtc {
+            user: self.user.pubkey(),
+            squad_multisig: ms,
+            config: self.config,
+            zbtc_mint: self.zbtc_mint,
+            sbtc_mint: self.sbtc_mint,
+            user_zbtc_account: self.user_zbtc,
+            user_sbtc_account: self.user_sbtc,
+            treasury_zbtc_vault: self.treasury_vault,
+            fee_vault: self.fee_vault,
+            treasury_authority_pda: pda(&[b"treasury_auth_v1", ms.as_ref()], &otc_swap::ID),
+            fee_authority_pda: pda(&[b"fee_auth_v1", ms.as_ref()], &otc_swap::ID),
+            zbtc_price_update: self.price_update,
+            authorized_sbtc_oracle_state_pda: self.oracle_state,
+            token_program: spl_token::ID,
+        };
+        let ix = Instruction::new_with_bytes(otc_swap::ID, &data, accounts.to_account_metas(None));
+        let user = self.user.insecure_clone();
+        self.send(ix, &[&user])
+    }
+
+    fn quote_mint(&self, zbtc_amount: u64) -> Result<Quote, TransactionError> {
+        self.simulate_view(otc_swap::instruction::QuoteMint { zbtc_amount }.data())
+    }
+
+    fn quote_burn(&self, sbtc_amount: u64) -> Result<Quote, TransactionError> {
+        self.simulate_view(otc_swap::instruction::QuoteBurn { sbtc_amount }.data())
+    }
+
+    fn system_health(&self) -> Result<SystemHealth, TransactionError> {
+        self.simulate_view(otc_swap::instruction::GetSystemHealth {}.data())
+    }
+
+    /// Simulates a `TreasuryView` instruction with the user as fee payer and decodes its return data.
+    fn simulate_view<T: AnchorDeserialize>(&self, data: Vec<u8>) -> Result<T, TransactionError> {
+        let accounts = otc_swap::accounts::TreasuryView {
+            squad_multisig: self.multisig.pubkey(),
+            config: self.config,
+            treasury_zbtc_vault: self.treasury_vault,
+            zbtc_price_update: self.price_update,
+            authorized_sbtc_oracle_state_pda: self.oracle_state,
+        };
+        let ix = Instruction::new_with_bytes(otc_swap::ID, &data, accounts.to_account_metas(None));
+        let payer = self.user.pubkey();
+        let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer), &[&self.user], self.svm.latest_blockhash());
+        let info = self.svm.simulate_transaction(tx).map_err(|failed| failed.err)?;
+        Ok(T::try_from_slice(&info.meta.return_data.data).unwrap())
+    }
+
+    fn set_guardian(&mut self, signer: &Keypair, guardian: Option<Pubkey>) -> TransactionResult {
+        let accounts = otc_swap::accounts::UpdateConfig { squad_multisig: signer.pubkey(), config: self.config };
+        let data = otc_swap::instruction::SetGuardian { guardian }.data();
+        let ix = Instruction::new_with_bytes(otc_swap::ID, &data, accounts.to_account_metas(None));
+        self.send(ix, &[signer])
+    }
+
+    fn pause(&mut self, authority: &Keypair, pause_mint: bool, pause_burn: bool) -> TransactionResult {
+        let accounts = otc_swap::accounts::Pause {
+            authority: authority.pubkey(),
+            squad_multisig: self.multisig.pubkey(),
+            config: self.config,
+        };
+        let data = otc_swap::instruction::Pause { pause_mint, pause_burn }.data();
+        let ix = Instruction::new_with_bytes(otc_swap::ID, &data, accounts.to_account_metas(None));
+        self.send(ix, &[authority])
+    }
+
+    fn unpause(&mut self, signer: &Keypair, unpause_mint: bool, unpause_burn: bool) -> TransactionResult {
+        let accounts = otc_swap::accounts::UpdateConfig { squad_multisig: signer.pubkey(), config: self.config };
+        let data = otc_swap::instruction::Unpause { unpause_mint, unpause_burn }.data();
+        let ix = Instruction::new_with_bytes(otc_swap::ID, &data, accounts.to_account_metas(None));
+        self.send(ix, &[signer])
+    }
+
+    fn token_amount(&self, address: Pubkey) -> u64 {
+        let account = self.svm.get_account(&address).unwrap();
+        spl_token::state::Account::unpack(&account.data).unwrap().amount
+    }
+
+    fn mint_state(&self, address: Pubkey) -> spl_token::state::Mint {
+        spl_token::state::Mint::unpack(&self.svm.get_account(&address).unwrap().data).unwrap()
+    }
+
+    fn config(&self) -> Config {
+        let account = self.svm.get_account(&self.config).unwrap();
+        Config::try_deserialize(&mut &account.data[..]).unwrap()
+    }
+
+    fn deposit_collateral(&mut self, amount: u64) -> TransactionResult {
+        let (ms, user) = (self.multisig.pubkey(), self.user.pubkey());
+        let accounts = otc_swap::accounts::DepositCollateral {
+            depositor: user,
+            squad_multisig: ms,
+            config: self.config,
+            depositor_zbtc_account: self.user_zbtc,
+            treasury_zbtc_vault: self.treasury_vault,
+            depositor_record: self.depositor_record(),
+            zbtc_price_update: self.price_update,
+            authorized_sbtc_oracle_state_pda: self.oracle_state,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+        };
+        let data = otc_swap::instruction::DepositCollateral { amount }.data();
+        let ix = Instruction::new_with_bytes(otc_swap::ID, &data, accounts.to_account_metas(None));
+        let user = self.user.insecure_clone();
+        self.send(ix, &[&user])
+    }
+
+    fn withdraw_excess_collateral(&mut self, amount: u64) -> TransactionResult {
+        let ms = self.multisig.pubkey();
+        let accounts = otc_swap::accounts::WithdrawExcessCollateral {
+            squad_multisig: ms,
+            config: self.config,
+            treasury_zbtc_vault: self.treasury_vault,
+            treasury_authority_pda: pda(&[b"treasury_auth_v1", ms.as_ref()], &otc_swap::ID),
+            destination: self.user_zbtc,
+            zbtc_price_update: self.price_update,
+            authorized_sbtc_oracle_state_pda: self.oracle_state,
+            token_program: spl_token::ID,
+        };
+        let data = otc_swap::instruction::WithdrawExcessCollateral { amount }.data();
+        let ix = Instruction::new_with_bytes(otc_swap::ID, &data, accounts.to_account_metas(None));
+        let multisig = self.multisig.insecure_clone();
+        self.send(ix, &[&multisig])
+    }
+
+    fn depositor_record(&self) -> Pubkey {
+        pda(&[b"depositor_v1", self.config.as_ref(), self.user.pubkey().as_ref()], &otc_swap::ID)
+    }
+
+    fn oracle(&self) -> OracleState {
+        let account = self.svm.get_account(&self.oracle_state).unwrap();
+        OracleState::try_deserialize(&mut &account.data[..]).unwrap()
+    }
+
+    /// Creates the `["trend"]` state on the zBTC feed, signed by `authority`.
+    fn initialize_trend(&mut self, authority: &Keypair, min_observation_interval: i64) -> TransactionResult {
+        let accounts = sbtc_oracle::accounts::InitializeTrend {
+            oracle_state: self.oracle_state,
+            trend_state: self.trend_state(),
+            authority: authority.pubkey(),
+            system_program: system_program::ID,
+        };
+        let data =
+            sbtc_oracle::instruction::InitializeTrend { feed_id: FEED_ID, min_observation_interval, spot_weight_bps: 0 }
+                .data();
+        let ix = Instruction::new_with_bytes(sbtc_oracle::ID, &data, accounts.to_account_metas(None));
+        self.send(ix, &[authority])
+    }
+
+    fn record_observation(&mut self) -> TransactionResult {
+        let accounts = sbtc_oracle::accounts::RecordObservation {
+            trend_state: self.trend_state(),
+            oracle_state: self.oracle_state,
+            price_update: self.price_update,
+        };
+        let data = sbtc_oracle::instruction::RecordObservation {}.data();
+        let ix = Instruction::new_with_bytes(sbtc_oracle::ID, &data, accounts.to_account_metas(None));
+        let user = self.user.insecure_clone();
+        self.send(ix, &[&user])
+    }
+
+    fn trend_state(&self) -> Pubkey {
+        pda(&[b"trend"], &sbtc_oracle::ID)
+    }
+}
+
+#[test]
+fn initialize_hands_sbtc_mint_authority_to_pda() {
+    let harness = Harness::new(10 * ONE_BTC);
+    let ms = harness.multisig.pubkey();
+
+    let mint = harness.mint_state(harness.sbtc_mint);
+    let mint_authority = pda(&[b"sbtc_mint_authority", ms.as_ref()], &otc_swap::ID);
+    assert_eq!(mint.mint_authority, Some(mint_authority).into());
+    // Freeze authority stays with the multisig
+    assert_eq!(mint.freeze_authority, Some(ms).into());
+
+    let config = harness.config();
+    assert_eq!(config.squad_multisig, ms);
+    assert_eq!(config.fee_rate_bps, FEE_RATE_BPS);
+    assert_eq!(config.authorized_sbtc_oracle_state_pda, harness.oracle_state);
+    assert_eq!(config.sbtc_oracle_program_id, sbtc_oracle::ID);
+}
+
+#[test]
+fn mint_then_burn_round_trip() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+
+    harness.mint_sbtc(ONE_BTC).unwrap();
+    let minted = harness.token_amount(harness.user_sbtc);
+    assert!(minted > 0);
+    assert_eq!(harness.token_amount(harness.user_zbtc), 9 * ONE_BTC);
+    assert_eq!(harness.token_amount(harness.fee_vault), ONE_BTC * FEE_RATE_BPS / 10_000);
+    assert_eq!(harness.config().total_sbtc_outstanding, minted as u128);
+
+    harness.burn_sbtc(minted).unwrap();
+    assert_eq!(harness.token_amount(harness.user_sbtc), 0);
+    assert_eq!(harness.config().total_sbtc_outstanding, 0);
+    // Fees and the confidence interval mean the user always gets back less than deposited
+    assert!(harness.token_amount(harness.user_zbtc) < 10 * ONE_BTC);
+}
+
+#[test]
+fn stale_zbtc_price_is_rejected() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+
+    harness.warp_to(NOW + 301);
+    // Keep the sBTC side fresh so only the Pyth update is stale
+    harness.submit_sbtc_price(SBTC_PRICE_CENTS).unwrap();
+    let err = harness.mint_sbtc(ONE_BTC).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::StaleOraclePrice));
+
+    harness.set_zbtc_price(ZBTC_PRICE, NOW + 301);
+    harness.mint_sbtc(ONE_BTC).unwrap();
+}
+
+#[test]
+fn stale_sbtc_price_is_rejected() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+
+    harness.warp_to(NOW + 301);
+    harness.set_zbtc_price(ZBTC_PRICE, NOW + 301);
+    // The only submission has aged out of the median
+    let err = harness.mint_sbtc(ONE_BTC).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::InsufficientOracleQuorum));
+}
+
+#[test]
+fn mint_without_collateral_buffer_fails() {
+    let mut harness = Harness::new(0);
+
+    let err = harness.mint_sbtc(ONE_BTC).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::InsufficientCollateral));
+    assert_eq!(harness.token_amount(harness.user_zbtc), 10 * ONE_BTC);
+}
+
+#[test]
+fn guardian_pauses_and_only_multisig_unpauses() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+    let guardian = Keypair::new();
+    harness.svm.airdrop(&guardian.pubkey(), 1_000_000_000).unwrap();
+    let multisig = harness.multisig.insecure_clone();
+
+    // Pausing needs the multisig or the guardian
+    let err = harness.pause(&guardian, true, false).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::Unauthorized));
+    harness.set_guardian(&multisig, Some(guardian.pubkey())).unwrap();
+
+    harness.mint_sbtc(ONE_BTC).unwrap();
+    harness.pause(&guardian, true, false).unwrap();
+    let err = harness.mint_sbtc(ONE_BTC).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::Paused));
+
+    // Burn stays open while only mint is paused
+    let minted = harness.token_amount(harness.user_sbtc);
+    harness.burn_sbtc(minted / 2).unwrap();
+
+    // The guardian cannot unpause: its config PDA does not exist
+    assert!(harness.unpause(&guardian, true, false).is_err());
+    harness.unpause(&multisig, true, false).unwrap();
+    harness.mint_sbtc(ONE_BTC).unwrap();
+}
+
+#[test]
+fn only_multisig_sets_guardian() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+    let intruder = Keypair::new();
+    harness.svm.airdrop(&intruder.pubkey(), 1_000_000_000).unwrap();
+
+    // `UpdateConfig` derives the config PDA from the signer, so another key cannot reach it
+    assert!(harness.set_guardian(&intruder, Some(intruder.pubkey())).is_err());
+    assert_eq!(harness.config().guardian, None);
+}
+
+#[test]
+fn slippage_and_deadline_bound_swaps() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+
+    let err = harness.mint_sbtc_with_limits(ONE_BTC, u64::MAX, None).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::SlippageExceeded));
+    let err = harness.mint_sbtc_with_limits(ONE_BTC, 0, Some(NOW - 1)).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::DeadlineExceeded));
+
+    harness.mint_sbtc_with_limits(ONE_BTC, 1, Some(NOW)).unwrap();
+    let minted = harness.token_amount(harness.user_sbtc);
+
+    let err = harness.burn_sbtc_with_limits(minted, ONE_BTC, None).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::SlippageExceeded));
+    let err = harness.burn_sbtc_with_limits(minted, 0, Some(NOW - 1)).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::DeadlineExceeded));
+    harness.burn_sbtc_with_limits(minted, 1, Some(NOW)).unwrap();
+}
+
+#[test]
+fn exact_out_swaps_deliver_the_requested_amount() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+
+    let err = harness.mint_sbtc_exact_out(ONE_BTC, ONE_BTC / 2).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::SlippageExceeded));
+    harness.mint_sbtc_exact_out(ONE_BTC, 2 * ONE_BTC).unwrap();
+    assert_eq!(harness.token_amount(harness.user_sbtc), ONE_BTC);
+    // 1 sBTC at $95,000 costs a little over 0.95 zBTC at the bid, plus fees
+    let zbtc_in = 10 * ONE_BTC - harness.token_amount(harness.user_zbtc);
+    assert!(zbtc_in > 95_000_000 && zbtc_in < 96_000_000, "{zbtc_in}");
+
+    let zbtc_before = harness.token_amount(harness.user_zbtc);
+    let err = harness.burn_sbtc_exact_out(ONE_BTC / 2, ONE_BTC / 2).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::SlippageExceeded));
+    harness.burn_sbtc_exact_out(ONE_BTC / 2, ONE_BTC).unwrap();
+    assert_eq!(harness.token_amount(harness.user_zbtc), zbtc_before + ONE_BTC / 2);
+    assert!(harness.token_amount(harness.user_sbtc) < ONE_BTC / 2);
+}
+
+#[test]
+fn quotes_match_executed_swaps() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+
+    let quote = harness.quote_mint(ONE_BTC).unwrap();
+    harness.mint_sbtc(ONE_BTC).unwrap();
+    let minted = harness.token_amount(harness.user_sbtc);
+    assert_eq!(quote.output, minted);
+    assert_eq!(quote.fee, harness.token_amount(harness.fee_vault));
+    // Mint values zBTC at the bid, $100,000 - $10
+    assert_eq!(quote.zbtc_price_cents, 9_999_000);
+    assert_eq!(quote.sbtc_price_cents, SBTC_PRICE_CENTS);
+    assert!(quote.collateral_ratio_after >= MIN_COLLATERAL_BPS);
+
+    // Quotes fail wherever the swap would
+    let multisig = harness.multisig.insecure_clone();
+    harness.pause(&multisig, true, true).unwrap();
+    assert_eq!(harness.quote_mint(ONE_BTC).unwrap_err(), custom_error(ErrorCode::Paused));
+    assert_eq!(harness.quote_burn(minted).unwrap_err(), custom_error(ErrorCode::Paused));
+    assert_eq!(harness.burn_sbtc(minted).unwrap_err().err, custom_error(ErrorCode::Paused));
+    harness.unpause(&multisig, true, true).unwrap();
+
+    let quote = harness.quote_burn(minted).unwrap();
+    let (zbtc_before, fees_before) = (harness.token_amount(harness.user_zbtc), harness.token_amount(harness.fee_vault));
+    harness.burn_sbtc(minted).unwrap();
+    assert_eq!(quote.output, harness.token_amount(harness.user_zbtc) - zbtc_before);
+    assert_eq!(quote.fee, harness.token_amount(harness.fee_vault) - fees_before);
+    assert_eq!(quote.zbtc_price_cents, 10_001_000);
+    assert_eq!(quote.collateral_ratio_after, u64::MAX);
+
+    // Nothing is outstanding any more, so there is nothing to redeem against
+    assert_eq!(harness.quote_burn(ONE_BTC).unwrap_err(), custom_error(ErrorCode::InvalidAmount));
+}
+
+#[test]
+fn system_health_reports_collateral_ratio() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+
+    let health = harness.system_health().unwrap();
+    assert_eq!(health.treasury_balance, 10 * ONE_BTC);
+    assert_eq!(health.total_sbtc_outstanding, 0);
+    assert_eq!(health.required_collateral, 0);
+    assert_eq!(health.collateral_ratio_bps, u64::MAX);
+
+    harness.mint_sbtc(ONE_BTC).unwrap();
+    let health = harness.system_health().unwrap();
+    let minted = harness.token_amount(harness.user_sbtc);
+    assert_eq!(health.treasury_balance, harness.token_amount(harness.treasury_vault));
+    assert_eq!(health.total_sbtc_outstanding, minted as u128);
+    // ~0.95 zBTC backs the sBTC at mid prices; the treasury holds ~11 zBTC
+    let backing = health.required_collateral * 10_000 / MIN_COLLATERAL_BPS as u128;
+    assert!(backing > 94_000_000 && backing < 96_000_000, "{backing}");
+    assert_eq!(health.collateral_ratio_bps as u128, health.treasury_balance as u128 * 10_000 / backing);
+}
+
+#[test]
+fn under_collateralised_burns_redeem_pro_rata() {
+    let mut harness = Harness::new(ONE_BTC);
+
+    harness.mint_sbtc(ONE_BTC / 2).unwrap();
+    let minted = harness.token_amount(harness.user_sbtc);
+
+    // At $25k the ~1.5 zBTC treasury is worth less than the ~$50k of sBTC outstanding
+    harness.set_zbtc_price(ZBTC_PRICE / 4, NOW);
+    let health = harness.system_health().unwrap();
+    assert!(health.collateral_ratio_bps < 10_000, "{}", health.collateral_ratio_bps);
+
+    let half = minted / 2;
+    let treasury = harness.token_amount(harness.treasury_vault);
+    let share = half as u128 * treasury as u128 / minted as u128;
+    let fee = share * FEE_RATE_BPS as u128 / 10_000;
+    let quote = harness.quote_burn(half).unwrap();
+    assert_eq!((quote.output + quote.fee) as u128, share);
+
+    let zbtc_before = harness.token_amount(harness.user_zbtc);
+    let event = burn_event(harness.burn_sbtc(half));
+    assert!(event.pro_rata);
+    assert_eq!((harness.token_amount(harness.user_zbtc) - zbtc_before) as u128, share - fee);
+
+    // The last holder gets the same share, leaving only rounding dust behind
+    harness.burn_sbtc(minted - half).unwrap();
+    assert_eq!(harness.config().total_sbtc_outstanding, 0);
+    assert!(harness.token_amount(harness.treasury_vault) <= 1);
+}
+
+#[test]
+fn partially_buffered_burns_redeem_at_oracle_value() {
+    let mut harness = Harness::new(ONE_BTC);
+
+    harness.mint_sbtc(ONE_BTC / 2).unwrap();
+    let minted = harness.token_amount(harness.user_sbtc);
+
+    // At $50k the ~1.5 zBTC treasury is below the 200% buffer but still covers every sBTC
+    harness.set_zbtc_price(ZBTC_PRICE / 2, NOW);
+    let health = harness.system_health().unwrap();
+    assert!((10_000..20_000).contains(&health.collateral_ratio_bps), "{}", health.collateral_ratio_bps);
+
+    let half = minted / 2;
+    let treasury = harness.token_amount(harness.treasury_vault);
+    let share = half as u128 * treasury as u128 / minted as u128;
+    let quote = harness.quote_burn(half).unwrap();
+    assert!(((quote.output + quote.fee) as u128) < share);
+
+    let zbtc_before = harness.token_amount(harness.user_zbtc);
+    let event = burn_event(harness.burn_sbtc(half));
+    assert!(!event.pro_rata);
+    assert_eq!(harness.token_amount(harness.user_zbtc) - zbtc_before, quote.output);
+
+    let event = burn_event(harness.burn_sbtc_exact_out(quote.output / 2, u64::MAX));
+    assert!(!event.pro_rata);
+}
+
+#[test]
+fn only_upgrade_authority_initializes_sbtc_oracle() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+
+    // Re-create the state so initialize runs again, this time from a stranger
+    harness.svm.set_account(harness.oracle_state, Account::default()).unwrap();
+    let stranger = Keypair::new();
+    harness.svm.airdrop(&stranger.pubkey(), 10_000_000_000).unwrap();
+    let err = harness.send_init_sbtc_oracle(&stranger).unwrap_err();
+    assert_eq!(
+        err.err,
+        TransactionError::InstructionError(0, InstructionError::Custom(sbtc_oracle::ErrorCode::Unauthorized.into()))
+    );
+
+    let multisig = harness.multisig.insecure_clone();
+    harness.send_init_sbtc_oracle(&multisig).unwrap();
+}
+
+#[test]
+fn trend_crank_publishes_once_warm() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+
+    // Only the oracle authority can create the singleton trend
+    let user = harness.user.insecure_clone();
+    let err = harness.initialize_trend(&user, 1).unwrap_err();
+    assert_eq!(
+        err.err,
+        TransactionError::InstructionError(0, InstructionError::Custom(sbtc_oracle::ErrorCode::Unauthorized.into()))
+    );
+    let multisig = harness.multisig.insecure_clone();
+    harness.initialize_trend(&multisig, 1).unwrap();
+    let trend_state = harness.trend_state();
+    harness.add_publisher(trend_state);
+
+    for i in 0..SMA_PERIOD as i64 {
+        harness.warp_to(NOW + i);
+        harness.set_zbtc_price(ZBTC_PRICE, NOW + i);
+        harness.record_observation().unwrap();
+
+        let submission = *harness.oracle().submissions.iter().find(|s| s.publisher == trend_state).unwrap();
+        if i < SMA_PERIOD as i64 - 1 {
+            assert_eq!(submission.price, 0);
+        } else {
+            // $100,000 in cents, submitted as the trend PDA
+            assert_eq!((submission.price, submission.timestamp), (10_000_000, NOW + i));
+        }
+    }
+}
+
+#[test]
+fn legacy_oracle_state_migrates_to_publisher_layout() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+
+    // Roll the state back to the single-price layout
+    let legacy = LegacyOracleState {
+        trend_value: SBTC_PRICE_CENTS,
+        last_update: NOW,
+        authority: harness.multisig.pubkey(),
+        bump: harness.oracle().bump,
+    };
+    let mut data = OracleState::DISCRIMINATOR.to_vec();
+    legacy.serialize(&mut data).unwrap();
+    harness.set_account(harness.oracle_state, sbtc_oracle::ID, data);
+    let err = harness.mint_sbtc(ONE_BTC).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::InvalidOracleData));
+
+    let accounts = sbtc_oracle::accounts::MigrateOracleState {
+        authority: harness.multisig.pubkey(),
+        oracle_state: harness.oracle_state,
+        system_program: system_program::ID,
+    };
+    let data = sbtc_oracle::instruction::MigrateOracleState {}.data();
+    let ix = Instruction::new_with_bytes(sbtc_oracle::ID, &data, accounts.to_account_metas(None));
+    let user = harness.user.insecure_clone();
+    let err = harness.send(ix.clone(), &[&user]).unwrap_err();
+    assert_eq!(
+        err.err,
+        TransactionError::InstructionError(0, InstructionError::Custom(sbtc_oracle::ErrorCode::Unauthorized.into()))
+    );
+    let multisig = harness.multisig.insecure_clone();
+    harness.send(ix.clone(), &[&multisig]).unwrap();
+
+    let oracle = harness.oracle();
+    assert_eq!((oracle.authority, oracle.submissions.len()), (multisig.pubkey(), 0));
+    // Already migrated
+    let err = harness.send(ix, &[&multisig]).unwrap_err();
+    assert_eq!(
+        err.err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(sbtc_oracle::ErrorCode::InvalidOracleState.into())
+        )
+    );
+
+    // Publishers are whitelisted again and swaps resume
+    for publisher in harness.publishers.iter().map(Keypair::pubkey).collect::<Vec<_>>() {
+        harness.add_publisher(publisher);
+    }
+    harness.submit_sbtc_price(SBTC_PRICE_CENTS).unwrap();
+    harness.mint_sbtc(ONE_BTC).unwrap();
+}
+
+#[test]
+fn single_publisher_cannot_set_the_sbtc_price() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+    assert_eq!(harness.config().sbtc_oracle_min_publishers, 3);
+
+    harness.warp_to(NOW + 301);
+    harness.set_zbtc_price(ZBTC_PRICE, NOW + 301);
+    // Only one of the three publishers is fresh
+    let publisher = harness.publishers[0].insecure_clone();
+    let accounts =
+        sbtc_oracle::accounts::SubmitPrice { oracle_state: harness.oracle_state, publisher: publisher.pubkey() };
+    let data = sbtc_oracle::instruction::SubmitPrice { price: SBTC_PRICE_CENTS }.data();
+    let ix = Instruction::new_with_bytes(sbtc_oracle::ID, &data, accounts.to_account_metas(None));
+    harness.send(ix, &[&publisher]).unwrap();
+
+    let err = harness.mint_sbtc(ONE_BTC).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::InsufficientOracleQuorum));
+}
+
+#[test]
+fn deposits_do_not_depend_on_the_oracles() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+
+    // Both oracles are stale: swaps halt but the treasury can still be topped up
+    harness.warp_to(NOW + 3_600);
+    let err = harness.mint_sbtc(ONE_BTC).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::StaleOraclePrice));
+    harness.deposit_collateral(ONE_BTC).unwrap();
+    harness.deposit_collateral(ONE_BTC).unwrap();
+
+    assert_eq!(harness.token_amount(harness.treasury_vault), 12 * ONE_BTC);
+    let account = harness.svm.get_account(&harness.depositor_record()).unwrap();
+    let record = DepositorRecord::try_deserialize(&mut &account.data[..]).unwrap();
+    assert_eq!((record.total_deposited, record.last_deposit_at), (2 * ONE_BTC, NOW + 3_600));
+}
+
+#[test]
+fn excess_withdrawal_keeps_the_mint_collateral_check_satisfied() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+    harness.mint_sbtc(ONE_BTC).unwrap();
+
+    // Excess at mid prices overstates what mint's bid-priced check leaves free
+    let health = harness.system_health().unwrap();
+    let excess_at_mid = health.treasury_balance - health.required_collateral as u64;
+    let err = harness.withdraw_excess_collateral(excess_at_mid).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::InsufficientCollateral));
+
+    // A 10% sBTC jump trips the same circuit breaker as swaps
+    harness.submit_sbtc_price(SBTC_PRICE_CENTS * 11 / 10).unwrap();
+    let err = harness.withdraw_excess_collateral(ONE_BTC).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::SbtcPriceChangeTooFast));
+
+    harness.submit_sbtc_price(SBTC_PRICE_CENTS).unwrap();
+    // The bid sits 1 bps below mid, so ~0.0002 zBTC of the mid excess is still required
+    harness.withdraw_excess_collateral(excess_at_mid - ONE_BTC / 1_000).unwrap();
+    harness.mint_sbtc(1_000).unwrap();
+}
+
+#[test]
+fn mock_pyth_prices_are_rejected_without_mock_oracle() {
+    let mut harness = Harness::new(10 * ONE_BTC);
+
+    let mock_feed = pda(&[b"mock_v1"], &mock_pyth::ID);
+    let accounts = mock_pyth::accounts::SetFeed {
+        price_account: mock_feed,
+        authority: harness.user.pubkey(),
+        system_program: system_program::ID,
+    };
+    let data = mock_pyth::instruction::SetFeed {
+        price: ZBTC_PRICE,
+        conf: ZBTC_CONF,
+        expo: -8,
+        publish_time: NOW,
+        ema_price: ZBTC_PRICE,
+        ema_conf: ZBTC_CONF,
+        slot: 0,
+        proof_available_time: NOW as u64,
+        prev_publish_time: NOW - 1,
+    }
+    .data();
+    let ix = Instruction::new_with_bytes(mock_pyth::ID, &data, accounts.to_account_metas(None));
+    let user = harness.user.insecure_clone();
+    harness.send(ix, &[&user]).unwrap();
+
+    // The same price posted through mock-pyth is refused; only Pyth receiver updates count
+    harness.price_update = mock_feed;
+    let err = harness.mint_sbtc(ONE_BTC).unwrap_err();
+    assert_eq!(err.err, custom_error(ErrorCode::InvalidPythAccount));
+    assert_eq!(harness.token_amount(harness.user_zbtc), 10 * ONE_BTC);
+}
@@ -0,0 +1,430 @@
+// Stops Rust Analyzer complaining about missing configs
+// See https://solana.stackexchange.com/questions/17777
+#![allow(unexpected_cfgs)]
+
+// Fix warning: use of deprecated method `anchor_lang::prelude::AccountInfo::<'a>::realloc`: Use AccountInfo::resize() instead
+// See https://solana.stackexchange.com/questions/22979
+#![allow(deprecated)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+pub mod trend;
+
+declare_id!("8UDq3zAd8RqqkVVpCS8bRbRuWUQyDD6ioVVmtYtUCy6y");
+
+/// Max number of whitelisted publishers per oracle state
+pub const MAX_PUBLISHERS: usize = 8;
+/// Observations averaged into the on-chain trend
+pub const SMA_PERIOD: usize = 1000;
+/// Max age in seconds of a Pyth price update accepted as an observation
+pub const OBSERVATION_MAX_AGE: u64 = 60;
+
+#[program]
+pub mod sbtc_oracle {
+    use super::*;
+
+    /// Creates the singleton `["oracle"]` state. Only the program's upgrade authority can call it,
+    /// so the publisher whitelist cannot be claimed by front-running the deployment.
+    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+        let oracle_state = &mut ctx.accounts.oracle_state;
+        oracle_state.authority = ctx.accounts.authority.key();
+        oracle_state.bump = ctx.bumps.oracle_state;
+        oracle_state.submissions = Vec::new();
+
+        Ok(())
+    }
+
+    /// Rewrites an `["oracle"]` account created with the single-price `LegacyOracleState` layout
+    /// into `OracleState`, keeping its authority. The legacy price is not a publisher submission,
+    /// so it is dropped and publishers must be whitelisted again.
+    pub fn migrate_oracle_state(ctx: Context<MigrateOracleState>) -> Result<()> {
+        let state_info = ctx.accounts.oracle_state.to_account_info();
+        let legacy = {
+            let data = state_info.try_borrow_data()?;
+            require!(data.len() == 8 + LegacyOracleState::INIT_SPACE, ErrorCode::InvalidOracleState);
+            require!(data[..8] == *OracleState::DISCRIMINATOR, ErrorCode::InvalidOracleState);
+            LegacyOracleState::deserialize(&mut &data[8..])?
+        };
+        require_keys_eq!(legacy.authority, ctx.accounts.authority.key(), ErrorCode::Unauthorized);
+
+        let new_len = 8 + OracleState::INIT_SPACE;
+        let rent_exempt = Rent::get()?.minimum_balance(new_len);
+        let top_up = rent_exempt.saturating_sub(state_info.lamports());
+        if top_up > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: state_info.clone(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
+        state_info.realloc(new_len, true)?;
+
+        let oracle_state = OracleState {
+            authority: legacy.authority,
+            bump: ctx.bumps.oracle_state,
+            submissions: Vec::new(),
+        };
+        oracle_state.try_serialize(&mut &mut state_info.try_borrow_mut_data()?[..])?;
+
+        emit!(OracleStateMigratedEvent {
+            authority: legacy.authority,
+            legacy_trend_value: legacy.trend_value,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn add_publisher(ctx: Context<UpdatePublishers>, publisher: Pubkey) -> Result<()> {
+        let oracle_state = &mut ctx.accounts.oracle_state;
+        require!(oracle_state.submission_index(&publisher).is_none(), ErrorCode::PublisherAlreadyAdded);
+        require!(oracle_state.submissions.len() < MAX_PUBLISHERS, ErrorCode::TooManyPublishers);
+
+        oracle_state.submissions.push(Submission { publisher, price: 0, timestamp: 0 });
+
+        emit!(PublishersUpdatedEvent {
+            authority: ctx.accounts.authority.key(),
+            publisher,
+            added: true,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_publisher(ctx: Context<UpdatePublishers>, publisher: Pubkey) -> Result<()> {
+        let oracle_state = &mut ctx.accounts.oracle_state;
+        let index = oracle_state.submission_index(&publisher).ok_or(ErrorCode::PublisherNotFound)?;
+
+        oracle_state.submissions.swap_remove(index);
+
+        emit!(PublishersUpdatedEvent {
+            authority: ctx.accounts.authority.key(),
+            publisher,
+            added: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn submit_price(ctx: Context<SubmitPrice>, price: u64) -> Result<()> {
+        require!(price > 0, ErrorCode::InvalidPrice);
+
+        let publisher = ctx.accounts.publisher.key();
+        let timestamp = Clock::get()?.unix_timestamp;
+        let oracle_state = &mut ctx.accounts.oracle_state;
+        let index = oracle_state.submission_index(&publisher).ok_or(ErrorCode::Unauthorized)?;
+        oracle_state.submissions[index] = Submission { publisher, price, timestamp };
+
+        emit!(PriceSubmittedEvent {
+            publisher,
+            price,
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Creates the singleton `["trend"]` state, signed by the oracle authority. `feed_id` must be
+    /// the BTC feed otc-swap trusts, i.e. its `Config.zbtc_pyth_feed_id`; it cannot be changed
+    /// later as the held observations would mix feeds.
+    pub fn initialize_trend(
+        ctx: Context<InitializeTrend>,
+        feed_id: [u8; 32],
+        min_observation_interval: i64,
+        spot_weight_bps: u16,
+    ) -> Result<()> {
+        require!(feed_id != [0u8; 32], ErrorCode::InvalidTrendParams);
+        require!(min_observation_interval > 0, ErrorCode::InvalidTrendParams);
+        require!(spot_weight_bps <= 10_000, ErrorCode::InvalidTrendParams);
+
+        let mut trend_state = ctx.accounts.trend_state.load_init()?;
+        trend_state.authority = ctx.accounts.authority.key();
+        trend_state.feed_id = feed_id;
+        trend_state.min_observation_interval = min_observation_interval;
+        trend_state.spot_weight_bps = spot_weight_bps;
+        trend_state.bump = ctx.bumps.trend_state;
+
+        Ok(())
+    }
+
+    pub fn update_trend_params(
+        ctx: Context<UpdateTrendParams>,
+        min_observation_interval: i64,
+        spot_weight_bps: u16,
+    ) -> Result<()> {
+        require!(min_observation_interval > 0, ErrorCode::InvalidTrendParams);
+        require!(spot_weight_bps <= 10_000, ErrorCode::InvalidTrendParams);
+
+        let mut trend_state = ctx.accounts.trend_state.load_mut()?;
+        trend_state.min_observation_interval = min_observation_interval;
+        trend_state.spot_weight_bps = spot_weight_bps;
+
+        Ok(())
+    }
+
+    /// Permissionless crank: appends the current Pyth price to the SMA ring buffer and, once
+    /// `SMA_PERIOD` observations are held, submits the regularised trend as the `trend_state`
+    /// publisher. The `trend_state` PDA must have been whitelisted with `add_publisher`.
+    pub fn record_observation(ctx: Context<RecordObservation>) -> Result<()> {
+        let clock = Clock::get()?;
+        let trend_key = ctx.accounts.trend_state.key();
+        let mut trend_state = ctx.accounts.trend_state.load_mut()?;
+
+        let spot = trend::read_spot_price(&ctx.accounts.price_update, &trend_state.feed_id, &clock)?;
+        let event = trend_state.record(&mut ctx.accounts.oracle_state, trend_key, spot, clock.unix_timestamp)?;
+        emit!(event);
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + OracleState::INIT_SPACE,
+        seeds = [b"oracle"],
+        bump
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ ErrorCode::Unauthorized)]
+    pub program: Program<'info, crate::program::SbtcOracle>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ ErrorCode::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateOracleState<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: the legacy layout cannot be deserialized as `OracleState` until rewritten;
+    /// length, discriminator and authority are checked in the handler
+    #[account(
+        mut,
+        owner = crate::ID @ ErrorCode::InvalidOracleState,
+        seeds = [b"oracle"],
+        bump,
+    )]
+    pub oracle_state: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePublishers<'info> {
+    #[account(
+        mut,
+        seeds = [b"oracle"],
+        bump = oracle_state.bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitPrice<'info> {
+    #[account(
+        mut,
+        seeds = [b"oracle"],
+        bump = oracle_state.bump,
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+
+    pub publisher: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeTrend<'info> {
+    #[account(
+        seeds = [b"oracle"],
+        bump = oracle_state.bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + std::mem::size_of::<TrendState>(),
+        seeds = [b"trend"],
+        bump
+    )]
+    pub trend_state: AccountLoader<'info, TrendState>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateTrendParams<'info> {
+    #[account(
+        mut,
+        seeds = [b"trend"],
+        bump = trend_state.load()?.bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub trend_state: AccountLoader<'info, TrendState>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RecordObservation<'info> {
+    #[account(
+        mut,
+        seeds = [b"trend"],
+        bump = trend_state.load()?.bump,
+    )]
+    pub trend_state: AccountLoader<'info, TrendState>,
+
+    #[account(
+        mut,
+        seeds = [b"oracle"],
+        bump = oracle_state.bump,
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+
+    /// CHECK: owner, verification level and feed id are validated in `trend::read_spot_price`
+    pub price_update: UncheckedAccount<'info>,
+}
+
+/// sBTC/USD submissions from whitelisted publishers. otc-swap aggregates them
+/// into a median, so a single publisher cannot move the price on its own.
+#[account]
+#[derive(InitSpace)]
+pub struct OracleState {
+    /// Manages the publisher whitelist
+    pub authority: Pubkey,
+    pub bump: u8,
+    /// One slot per whitelisted publisher; `price == 0` until its first submission
+    #[max_len(MAX_PUBLISHERS)]
+    pub submissions: Vec<Submission>,
+}
+
+impl OracleState {
+    pub fn submission_index(&self, publisher: &Pubkey) -> Option<usize> {
+        self.submissions.iter().position(|s| s.publisher == *publisher)
+    }
+}
+
+/// `OracleState` as first deployed: a single authority-pushed price. Shares the `OracleState`
+/// discriminator, see `migrate_oracle_state`.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct LegacyOracleState {
+    /// sBTC price in USD cents
+    pub trend_value: u64,
+    pub last_update: i64,
+    pub authority: Pubkey,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace)]
+pub struct Submission {
+    pub publisher: Pubkey,
+    /// sBTC price in USD cents
+    pub price: u64,
+    pub timestamp: i64,
+}
+
+/// Ring buffer of BTC/USD observations backing the on-chain SMA trend.
+#[account(zero_copy)]
+pub struct TrendState {
+    /// Can change the observation interval and regularisation weight; the oracle authority
+    pub authority: Pubkey,
+    /// Pyth feed the observations are read from, matching otc-swap's `Config.zbtc_pyth_feed_id`
+    pub feed_id: [u8; 32],
+    /// Observed prices in USD cents, oldest overwritten first
+    pub observations: [u64; SMA_PERIOD],
+    /// Running sum of the held observations
+    pub sum: u64,
+    pub last_observation_at: i64,
+    /// Min seconds between two observations
+    pub min_observation_interval: i64,
+    /// Observations held, saturating at `SMA_PERIOD`
+    pub count: u16,
+    /// Slot the next observation is written to
+    pub head: u16,
+    /// Weight of the spot price blended into the SMA, in bps
+    pub spot_weight_bps: u16,
+    pub bump: u8,
+    pub _padding: [u8; 1],
+}
+
+#[event]
+pub struct TrendUpdatedEvent {
+    pub spot: u64,
+    pub sma: u64,
+    pub trend: u64,
+    pub observations: u16,
+    pub published: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PriceSubmittedEvent {
+    pub publisher: Pubkey,
+    pub price: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OracleStateMigratedEvent {
+    pub authority: Pubkey,
+    pub legacy_trend_value: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PublishersUpdatedEvent {
+    pub authority: Pubkey,
+    pub publisher: Pubkey,
+    pub added: bool,
+    pub timestamp: i64,
+}
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Price must be positive")]
+    InvalidPrice,
+    #[msg("Signer is not the oracle authority or a whitelisted publisher")]
+    Unauthorized,
+    #[msg("Publisher is already whitelisted")]
+    PublisherAlreadyAdded,
+    #[msg("Publisher is not whitelisted")]
+    PublisherNotFound,
+    #[msg("Publisher whitelist is full")]
+    TooManyPublishers,
+    #[msg("Invalid trend parameters")]
+    InvalidTrendParams,
+    #[msg("Observation interval has not elapsed")]
+    ObservationTooSoon,
+    #[msg("Invalid Pyth price update")]
+    InvalidPriceUpdate,
+    #[msg("Pyth price update is too old")]
+    StalePrice,
+    #[msg("Math overflow")]
+    MathOverflow,
+    #[msg("Oracle state is not in the legacy single-price layout")]
+    InvalidOracleState,
+}
@@ -0,0 +1,228 @@
+// On-chain SMA trend: ring buffer maintenance and the Pyth spot read feeding it.
+
+use anchor_lang::prelude::*;
+use pyth_solana_receiver_sdk::error::GetPriceError;
+use pyth_solana_receiver_sdk::price_update::{PriceUpdateV2, VerificationLevel};
+
+use crate::{
+    ErrorCode, OracleState, Submission, TrendState, TrendUpdatedEvent, OBSERVATION_MAX_AGE, SMA_PERIOD,
+};
+
+impl TrendState {
+    /// Appends `spot` observed at `now` and, once warm, writes the trend into the submission
+    /// slot of `publisher` (the `TrendState` PDA) in `oracle_state`.
+    pub fn record(
+        &mut self,
+        oracle_state: &mut OracleState,
+        publisher: Pubkey,
+        spot: u64,
+        now: i64,
+    ) -> Result<TrendUpdatedEvent> {
+        require!(
+            self.count == 0 || now >= self.last_observation_at.saturating_add(self.min_observation_interval),
+            ErrorCode::ObservationTooSoon
+        );
+
+        self.push(spot, now)?;
+        let trend = self.trend(spot);
+
+        let published = self.is_warm();
+        if published {
+            let index = oracle_state.submission_index(&publisher).ok_or(ErrorCode::PublisherNotFound)?;
+            oracle_state.submissions[index] = Submission { publisher, price: trend, timestamp: now };
+        }
+
+        Ok(TrendUpdatedEvent {
+            spot,
+            sma: self.sma(),
+            trend,
+            observations: self.count,
+            published,
+            timestamp: now,
+        })
+    }
+
+    /// Appends an observation, evicting the oldest once `SMA_PERIOD` are held.
+    pub fn push(&mut self, price: u64, timestamp: i64) -> Result<()> {
+        let head = self.head as usize;
+        if self.is_warm() {
+            self.sum -= self.observations[head];
+        } else {
+            self.count += 1;
+        }
+        self.observations[head] = price;
+        self.sum = self.sum.checked_add(price).ok_or(ErrorCode::MathOverflow)?;
+        self.head = ((head + 1) % SMA_PERIOD) as u16;
+        self.last_observation_at = timestamp;
+        Ok(())
+    }
+
+    /// Whether the buffer holds a full `SMA_PERIOD` of observations.
+    pub fn is_warm(&self) -> bool {
+        self.count as usize == SMA_PERIOD
+    }
+
+    /// Mean of the held observations, 0 when empty.
+    pub fn sma(&self) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        self.sum / self.count as u64
+    }
+
+    /// SMA regularised towards `spot` by `spot_weight_bps`.
+    pub fn trend(&self, spot: u64) -> u64 {
+        let weight = self.spot_weight_bps as u128;
+        let blended = (self.sma() as u128 * (10_000 - weight) + spot as u128 * weight) / 10_000;
+        // A weighted mean of two u64 values fits in u64
+        blended as u64
+    }
+}
+
+/// Reads a fully verified `PriceUpdateV2` for `feed_id` no older than `OBSERVATION_MAX_AGE`,
+/// in USD cents.
+pub fn read_spot_price(price_update: &AccountInfo, feed_id: &[u8; 32], clock: &Clock) -> Result<u64> {
+    require_keys_eq!(*price_update.owner, pyth_solana_receiver_sdk::ID, ErrorCode::InvalidPriceUpdate);
+    let update = PriceUpdateV2::try_deserialize(&mut &price_update.try_borrow_data()?[..])
+        .map_err(|_| ErrorCode::InvalidPriceUpdate)?;
+    require!(update.verification_level == VerificationLevel::Full, ErrorCode::InvalidPriceUpdate);
+
+    let price = update
+        .get_price_no_older_than(clock, OBSERVATION_MAX_AGE, feed_id)
+        .map_err(|err| match err {
+            GetPriceError::PriceTooOld => ErrorCode::StalePrice,
+            _ => ErrorCode::InvalidPriceUpdate,
+        })?;
+
+    price_to_cents(price.price, price.exponent)
+}
+
+/// Converts a Pyth `price * 10^expo` USD value to USD cents, truncating.
+pub fn price_to_cents(price: i64, expo: i32) -> Result<u64> {
+    require!(price > 0, ErrorCode::InvalidPrice);
+    let price = price as u64;
+
+    let shift = expo.checked_add(2).ok_or(ErrorCode::MathOverflow)?;
+    let scale = 10u64.checked_pow(shift.unsigned_abs()).ok_or(ErrorCode::MathOverflow)?;
+    let cents = if shift >= 0 { price.checked_mul(scale).ok_or(ErrorCode::MathOverflow)? } else { price / scale };
+
+    require!(cents > 0, ErrorCode::InvalidPrice);
+    Ok(cents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    fn trend_state(spot_weight_bps: u16) -> Box<TrendState> {
+        let mut state = Box::new(TrendState::zeroed());
+        state.spot_weight_bps = spot_weight_bps;
+        state
+    }
+
+    fn oracle_state(publishers: &[Pubkey]) -> OracleState {
+        OracleState {
+            authority: Pubkey::new_unique(),
+            bump: 255,
+            submissions: publishers.iter().map(|&publisher| Submission { publisher, ..Default::default() }).collect(),
+        }
+    }
+
+    #[test]
+    fn push_tracks_sum_until_warm() {
+        let mut state = trend_state(0);
+        state.push(100, 1).unwrap();
+        state.push(300, 2).unwrap();
+
+        assert_eq!((state.count, state.head, state.sum), (2, 2, 400));
+        assert_eq!(state.sma(), 200);
+        assert_eq!(state.last_observation_at, 2);
+        assert!(!state.is_warm());
+    }
+
+    #[test]
+    fn push_evicts_oldest_once_full() {
+        let mut state = trend_state(0);
+        for i in 0..SMA_PERIOD as u64 {
+            state.push(1_000 + i, i as i64).unwrap();
+        }
+        assert!(state.is_warm());
+        assert_eq!(state.head, 0);
+
+        state.push(5_000, 1_000).unwrap();
+        let expected: u64 = (1..SMA_PERIOD as u64).map(|i| 1_000 + i).sum::<u64>() + 5_000;
+        assert_eq!(state.sum, expected);
+        assert_eq!(state.count as usize, SMA_PERIOD);
+        assert_eq!(state.head, 1);
+    }
+
+    #[test]
+    fn trend_blends_spot_by_weight() {
+        let mut state = trend_state(2_500);
+        state.push(10_000, 1).unwrap();
+
+        assert_eq!(state.trend(10_000), 10_000);
+        assert_eq!(state.trend(14_000), 11_000);
+        state.spot_weight_bps = 0;
+        assert_eq!(state.trend(14_000), 10_000);
+        state.spot_weight_bps = 10_000;
+        assert_eq!(state.trend(14_000), 14_000);
+    }
+
+    #[test]
+    fn record_publishes_the_trend_once_warm() {
+        let (trend_key, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut oracle = oracle_state(&[other, trend_key]);
+        let mut state = trend_state(2_500);
+        state.min_observation_interval = 60;
+
+        for i in 0..SMA_PERIOD as i64 - 1 {
+            let event = state.record(&mut oracle, trend_key, 10_000, i * 60).unwrap();
+            assert!(!event.published);
+        }
+        assert_eq!(oracle.submissions[1], Submission { publisher: trend_key, price: 0, timestamp: 0 });
+
+        let now = (SMA_PERIOD as i64 - 1) * 60;
+        let event = state.record(&mut oracle, trend_key, 14_000, now).unwrap();
+        // SMA of 999 x 10_000 and one 14_000, blended 25% towards the 14_000 spot
+        let sma = (10_000 * (SMA_PERIOD as u64 - 1) + 14_000) / SMA_PERIOD as u64;
+        assert_eq!((event.sma, event.observations, event.published), (sma, SMA_PERIOD as u16, true));
+        assert_eq!(event.trend, (sma * 3 + 14_000) / 4);
+        assert_eq!(oracle.submissions[1], Submission { publisher: trend_key, price: event.trend, timestamp: now });
+        // Other publishers' slots are untouched
+        assert_eq!(oracle.submissions[0].price, 0);
+    }
+
+    #[test]
+    fn record_enforces_interval_and_whitelist() {
+        let trend_key = Pubkey::new_unique();
+        let mut oracle = oracle_state(&[]);
+        let mut state = trend_state(0);
+        state.min_observation_interval = 60;
+
+        state.record(&mut oracle, trend_key, 10_000, 0).unwrap();
+        assert_eq!(
+            state.record(&mut oracle, trend_key, 10_000, 59).err().unwrap(),
+            ErrorCode::ObservationTooSoon.into()
+        );
+        for i in 1..SMA_PERIOD as i64 - 1 {
+            state.record(&mut oracle, trend_key, 10_000, i * 60).unwrap();
+        }
+
+        // Warm but not whitelisted: the observation is rejected rather than silently dropped
+        let now = (SMA_PERIOD as i64 - 1) * 60;
+        assert_eq!(
+            state.record(&mut oracle, trend_key, 10_000, now).err().unwrap(),
+            ErrorCode::PublisherNotFound.into()
+        );
+    }
+
+    #[test]
+    fn price_to_cents_scales_by_exponent() {
+        assert_eq!(price_to_cents(10_000_000_000_000, -8).unwrap(), 10_000_000);
+        assert_eq!(price_to_cents(1_000, 0).unwrap(), 100_000);
+        assert!(price_to_cents(-1, -8).is_err());
+        assert!(price_to_cents(1, -8).is_err());
+    }
+}
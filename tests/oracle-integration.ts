@@ -54,6 +54,7 @@ describe("otc-swap-integration", () => {
   let feeVault: anchor.web3.PublicKey;
 
   let oracleStatePda: anchor.web3.PublicKey;
+  let oracleProgramData: anchor.web3.PublicKey;
   let pythPriceAccount: anchor.web3.Keypair;
   let pythPriceFeed: anchor.web3.PublicKey;
 
@@ -83,6 +84,11 @@ describe("otc-swap-integration", () => {
       [Buffer.from("oracle")],
       oracleProgram.programId
     );
+    // Only the oracle's upgrade authority may initialize it
+    [oracleProgramData] = PublicKey.findProgramAddressSync(
+      [oracleProgram.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
   
     console.log(`configPda:${configPda} sbtcMintAuthorityPda:${sbtcMintAuthorityPda} treasuryAuthorityPda:${treasuryAuthorityPda} feeAuthorityPda:${feeAuthorityPda}`);
 
@@ -151,24 +157,39 @@ describe("otc-swap-integration", () => {
     .accounts({
       oracleState: oracleStatePda,
       authority: admin.publicKey,
+      program: oracleProgram.programId,
+      programData: oracleProgramData,
       systemProgram: SystemProgram.programId,
     } as any)
     .rpc();
 
     console.log("Oracle initialized.");
 
-    const tx = await oracleProgram.methods
-      .updateTrendTest(NEW_ORACLE_TREND)
-      .accounts({
-        oracleState: oracleStatePda,
-        authority: admin.publicKey,
-      } as any)
-      .rpc();
+    // otc-swap needs SBTC_ORACLE_MIN_PUBLISHERS (3) fresh submissions; the admin pays the fees
+    const publishers = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+    for (const publisher of publishers) {
+      await oracleProgram.methods
+        .addPublisher(publisher.publicKey)
+        .accounts({
+          oracleState: oracleStatePda,
+          authority: admin.publicKey,
+        } as any)
+        .rpc();
+
+      await oracleProgram.methods
+        .submitPrice(NEW_ORACLE_TREND)
+        .accounts({
+          oracleState: oracleStatePda,
+          publisher: publisher.publicKey,
+        } as any)
+        .signers([publisher])
+        .rpc();
+    }
     
       console.log("oracle updated.");
 
     const oracleState = await oracleProgram.account.oracleState.fetch(oracleStatePda);
-    console.log("Oracle trend_value:", oracleState.trendValue.toString());
+    console.log("Oracle submissions:", oracleState.submissions.map((s) => s.price.toString()));
   });
 
   it("initialize", async () => {
@@ -221,7 +242,7 @@ describe("otc-swap-integration", () => {
     expect(config.feeVault.equals(feeVault)).to.be.true;
     expect(config.feeRateBps.toNumber()).to.equal(FEE_RATE_BPS);
     expect(config.minCollateralBps.toNumber()).to.equal(MIN_COLLATERAL_BPS);
-    expect(config.paused).to.be.false;
+    expect(config.mintPaused).to.be.false;
     expect(config.totalSbtcOutstanding.toString()).to.equal("0");
   });
 
@@ -274,7 +295,7 @@ describe("otc-swap-integration", () => {
     console.log("Total sBTC outstanding:", preConfig.totalSbtcOutstanding.toString());
 
     const tx = await otcProgram.methods
-    .mintSbtc(deposit)
+    .mintSbtc(deposit, new anchor.BN(0), null)
     .accounts({
       user: user.publicKey,
       squadMultisig: admin.publicKey,
@@ -356,7 +377,7 @@ describe("otc-swap-integration", () => {
     let fee = zbtcAmount.toNumber() * FEE_RATE_BPS / 10_000;
 
     const mintTx = await otcProgram.methods
-    .mintSbtc(zbtcAmount)
+    .mintSbtc(zbtcAmount, new anchor.BN(0), null)
     .accounts({
       user: user.publicKey,
       squadMultisig: admin.publicKey,
@@ -408,7 +429,7 @@ describe("otc-swap-integration", () => {
 
     try{ 
       const burnTx = await otcProgram.methods
-      .burnSbtc(burnAmount)
+      .burnSbtc(burnAmount, new anchor.BN(0), null)
       .accounts({
         user: user.publicKey,
         squadMultisig: admin.publicKey,